@@ -0,0 +1,41 @@
+;; Grim Trigger as a WebAssembly strategy plugin (see src/strategies/wasm_plugin.rs for the ABI):
+;; cooperates until the opponent defects once, then defects forever.
+;; Register it with `--plugin-dir plugins`, it is then called "wasm_grim_trigger".
+(module
+  (memory (export "memory") 1)
+
+  ;; Offset 0: whether the opponent has already defected in the current match
+  ;; Offset 16 onwards: input buffer (histories), grown as needed
+  (global $triggered_at i32 (i32.const 0))
+  (global $buffer i32 (i32.const 16))
+
+  (func (export "ipd_alloc") (param $len i32) (result i32)
+    (local $needed_pages i32)
+    ;; Grow the memory if the buffer does not fit
+    (local.set $needed_pages
+      (i32.sub
+        (i32.div_u (i32.add (i32.add (global.get $buffer) (local.get $len)) (i32.const 65535)) (i32.const 65536))
+        (memory.size)))
+    (if (i32.gt_s (local.get $needed_pages) (i32.const 0))
+      (then
+        (if (i32.eq (memory.grow (local.get $needed_pages)) (i32.const -1))
+          (then unreachable))))
+    (global.get $buffer))
+
+  (func (export "ipd_init") (param $params i32) (param $len i32) (result i32)
+    ;; No parameters needed, any are accepted
+    (i32.const 0))
+
+  (func (export "ipd_reset")
+    (i32.store8 (global.get $triggered_at) (i32.const 0)))
+
+  (func (export "ipd_next_move") (param $own i32) (param $opp i32) (param $len i32) (result i32)
+    ;; Only the opponent's last move needs to be checked, the flag remembers the older ones
+    (if (i32.gt_s (local.get $len) (i32.const 0))
+      (then
+        (if (i32.eq
+              (i32.load8_u (i32.add (local.get $opp) (i32.sub (local.get $len) (i32.const 1))))
+              (i32.const 68)) ;; 'D'
+          (then (i32.store8 (global.get $triggered_at) (i32.const 1))))))
+    (i32.load8_u (global.get $triggered_at)))
+)
@@ -0,0 +1,131 @@
+// Exact long run payoffs of matchups between two memory-one strategies.
+// The outcome of each turn (CC, CD, DC, DD, from the first player's point of view) is a 4-state
+// Markov chain, so the expected payoff per turn follows from its limiting distribution
+// instead of having to simulate thousands of turns.
+use crate::simulation::{MatchConfig, play_match};
+use crate::{MemoryOne, PayoffMatrix, Strategy};
+
+// Transition matrix rows/columns are indexed by these states (first player's move, second player's move)
+pub const STATES: [&str; 4] = ["CC", "CD", "DC", "DD"];
+
+// Number of times the (lazy) transition matrix is squared: it is raised to the power 2^64
+const SQUARINGS: usize = 64;
+
+pub type Matrix = [[f64; 4]; 4];
+
+pub struct ExactPayoffs {
+    // Long run probability of each state (see `STATES`)
+    pub distribution: [f64; 4],
+    pub payoff1: f64,
+    pub payoff2: f64,
+}
+
+pub struct Comparison {
+    pub exact: ExactPayoffs,
+    pub simulated1: f64,
+    pub simulated2: f64,
+}
+
+// Probability of actually cooperating when the intended move is Cooperate with probability `p`
+// and every move is flipped with probability `noise`
+fn with_noise(p: f64, noise: f64) -> f64 {
+    p * (1.0 - noise) + (1.0 - p) * noise
+}
+
+// Cooperation probabilities of both players for every state. The second player sees
+// the state from its own point of view, so CD and DC are swapped.
+fn cooperation_probabilities(player1: &MemoryOne, player2: &MemoryOne, noise: f64) -> [(f64, f64); 4] {
+    [
+        (player1.cc, player2.cc),
+        (player1.cd, player2.dc),
+        (player1.dc, player2.cd),
+        (player1.dd, player2.dd),
+    ].map(|(p, q)| (with_noise(p, noise), with_noise(q, noise)))
+}
+
+// Distribution over the states for a turn where the players cooperate with probabilities `p` and `q`
+fn outcome_distribution(p: f64, q: f64) -> [f64; 4] {
+    [p * q, p * (1.0 - q), (1.0 - p) * q, (1.0 - p) * (1.0 - q)]
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+pub fn transition_matrix(player1: &MemoryOne, player2: &MemoryOne, noise: f64) -> Matrix {
+    cooperation_probabilities(player1, player2, noise).map(|(p, q)| outcome_distribution(p, q))
+}
+
+// Long run (time averaged) distribution over the states, starting from the first turn.
+// Works for reducible and periodic chains as well (e.g. TFT vs TFT without noise, or TFT vs
+// Suspicious TFT), where the stationary distribution is not unique or is never reached:
+// the lazy chain (I + M) / 2 has the same stationary distributions as M, is aperiodic,
+// and its powers converge to the time average of the powers of M.
+pub fn stationary_distribution(player1: &MemoryOne, player2: &MemoryOne, noise: f64) -> [f64; 4] {
+    let transitions = transition_matrix(player1, player2, noise);
+    let mut lazy = [[0.0; 4]; 4];
+    for i in 0..4 {
+        for j in 0..4 {
+            lazy[i][j] = (transitions[i][j] + if i == j { 1.0 } else { 0.0 }) / 2.0;
+        }
+    }
+    for _ in 0..SQUARINGS {
+        lazy = multiply(&lazy, &lazy);
+        // Keep every row a probability distribution: otherwise rounding errors (rows adding up
+        // to slightly more than 1) are squared again and again until they overflow
+        for row in lazy.iter_mut() {
+            let total: f64 = row.iter().sum();
+            *row = row.map(|probability| probability / total);
+        }
+    }
+
+    let first = outcome_distribution(with_noise(player1.first, noise), with_noise(player2.first, noise));
+    let mut distribution = [0.0; 4];
+    for (j, probability) in distribution.iter_mut().enumerate() {
+        *probability = (0..4).map(|i| first[i] * lazy[i][j]).sum();
+    }
+    let total: f64 = distribution.iter().sum();
+    distribution.map(|probability| probability / total)
+}
+
+// Expected payoff per turn of both players in the long run, with the given payoffs
+pub fn exact_payoffs(player1: &MemoryOne, player2: &MemoryOne, noise: f64, payoffs: &PayoffMatrix) -> ExactPayoffs {
+    let distribution = stationary_distribution(player1, player2, noise);
+    let payoffs1 = [payoffs.reward, payoffs.sucker, payoffs.temptation, payoffs.punishment];
+    let payoffs2 = [payoffs.reward, payoffs.temptation, payoffs.sucker, payoffs.punishment];
+    let payoff1 = distribution.iter().zip(payoffs1).map(|(d, p)| d * p as f64).sum();
+    let payoff2 = distribution.iter().zip(payoffs2).map(|(d, p)| d * p as f64).sum();
+    ExactPayoffs { distribution, payoff1, payoff2 }
+}
+
+// Same as `exact_payoffs`, but only if both strategies are memory-one
+pub fn exact_payoffs_for(strategy1: &dyn Strategy, strategy2: &dyn Strategy, noise: f64, payoffs: &PayoffMatrix) -> Option<ExactPayoffs> {
+    Some(exact_payoffs(&strategy1.memory_one()?, &strategy2.memory_one()?, noise, payoffs))
+}
+
+// Simulates a match and compares its average payoffs per turn with the exact ones
+pub fn compare_with_simulation(
+    strategy1: &mut dyn Strategy,
+    strategy2: &mut dyn Strategy,
+    turns: usize,
+    noise: f64,
+    payoffs: &PayoffMatrix,
+) -> Result<Comparison, String> {
+    let not_memory_one = format!("Both '{}' and '{}' must be memory-one strategies", strategy1, strategy2);
+    if strategy1.memory_one().is_none() || strategy2.memory_one().is_none() {
+        return Err(not_memory_one);
+    }
+    let mut config = MatchConfig::new(turns).with_noise(noise);
+    config.payoffs = *payoffs;
+    let result = play_match(strategy1, strategy2, &config, |_| {})?;
+    // Solved after the match, as some strategies (e.g. ZD) adapt their probabilities to the payoffs
+    let exact = exact_payoffs_for(&*strategy1, &*strategy2, noise, payoffs).ok_or(not_memory_one)?;
+    let (simulated1, simulated2) = result.average_payoffs();
+    Ok(Comparison { exact, simulated1, simulated2 })
+}
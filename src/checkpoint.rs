@@ -0,0 +1,182 @@
+// Checkpoints of experiment runs, so that an interrupted run (Ctrl-C, crash) can be resumed without playing the
+// completed matches again. The state file has one JSON object per line: first the experiment (its resolved TOML),
+// then one line per completed match, appended and flushed as soon as the match ends:
+//
+//     {"experiment":"name = \"generosity\"\nturns = 200\n..."}
+//     {"repetition":1,"player1":0,"player2":1,"moves1":"CCdC...","moves2":"CCCc..."}
+//
+// Moves are C/D, in lowercase when noise flipped them. A last line cut by a crash is ignored (and overwritten).
+use crate::Move;
+use crate::experiment::{Experiment, MatchSummary};
+use crate::simulation::{MatchResult, Turn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+
+#[derive(Serialize, Deserialize)]
+struct StateHeader {
+    experiment: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateMatch {
+    repetition: usize,
+    player1: usize,
+    player2: usize,
+    moves1: String,
+    moves2: String,
+}
+
+// A match read from the state file: its summary and which moves were flipped by noise
+pub struct CompletedMatch {
+    pub summary: MatchSummary,
+    pub flipped1: Vec<bool>,
+    pub flipped2: Vec<bool>,
+}
+
+impl CompletedMatch {
+    // Turn by turn view of the match (e.g. to export it again)
+    pub fn turns(&self, experiment: &Experiment) -> Vec<Turn> {
+        let payoffs = experiment.match_config().payoffs;
+        let result = &self.summary.result;
+        (0..result.turns())
+            .map(|i| {
+                let (move1, move2) = (result.history1[i], result.history2[i]);
+                let (payoff1, payoff2) = payoffs.payoffs(move1, move2);
+                Turn { iteration: i + 1, move1, move2, payoff1, payoff2, flipped1: self.flipped1[i], flipped2: self.flipped2[i] }
+            })
+            .collect()
+    }
+}
+
+pub struct Checkpoint {
+    writer: BufWriter<File>,
+    path: String,
+    completed: HashMap<(usize, usize, usize), CompletedMatch>,
+}
+
+fn encode_moves(moves: &[Move], flipped: &[bool]) -> String {
+    moves.iter().zip(flipped)
+        .map(|(&m, &flipped)| match (m, flipped) {
+            (Move::Cooperate, false) => 'C',
+            (Move::Defect, false) => 'D',
+            (Move::Cooperate, true) => 'c',
+            (Move::Defect, true) => 'd',
+        })
+        .collect()
+}
+
+fn decode_moves(moves: &str) -> Option<(Vec<Move>, Vec<bool>)> {
+    moves.chars()
+        .map(|c| match c {
+            'C' => Some((Move::Cooperate, false)),
+            'D' => Some((Move::Defect, false)),
+            'c' => Some((Move::Cooperate, true)),
+            'd' => Some((Move::Defect, true)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|moves| moves.into_iter().unzip())
+}
+
+// The configuration of an experiment that determines its results (i.e. without its outputs)
+fn results_configuration(experiment: &Experiment) -> String {
+    let mut experiment = experiment.clone();
+    experiment.output = Default::default();
+    experiment.to_toml()
+}
+
+impl Checkpoint {
+    // Starts a new state file (overwriting it, if it exists)
+    pub fn create(path: &str, experiment: &Experiment) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create state file '{}': {}", path, e))?;
+        let mut checkpoint = Self { writer: BufWriter::new(file), path: path.to_string(), completed: HashMap::new() };
+        let header = StateHeader { experiment: experiment.to_toml() };
+        checkpoint.write_line(&serde_json::to_string(&header).expect("the state header is valid JSON"))?;
+        Ok(checkpoint)
+    }
+
+    // The experiment a state file was written for
+    pub fn read_experiment(path: &str) -> Result<Experiment, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read state file '{}': {}", path, e))?;
+        let header: StateHeader = contents.lines().next()
+            .and_then(|line| serde_json::from_str(line).ok())
+            .ok_or_else(|| format!("'{}' is not a state file", path))?;
+        Experiment::parse(&header.experiment, path)
+    }
+
+    // Continues an existing state file: its completed matches are loaded (to be skipped) and new ones appended.
+    // `experiment` must give the same results as the one the state file was written for (its outputs may differ).
+    pub fn resume(path: &str, experiment: &Experiment) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read state file '{}': {}", path, e))?;
+        if results_configuration(&Self::read_experiment(path)?) != results_configuration(experiment) {
+            return Err(format!("State file '{}' was written for a different experiment", path));
+        }
+
+        let payoffs = experiment.match_config().payoffs;
+        let mut completed = HashMap::new();
+        // Bytes of the file up to the last valid line (anything after it is a line cut by a crash)
+        let mut valid_len = 0;
+        for (number, line) in contents.split_inclusive('\n').enumerate() {
+            if number > 0 {
+                let Some(state) = serde_json::from_str::<StateMatch>(line).ok().filter(|_| line.ends_with('\n')) else {
+                    break;
+                };
+                let invalid = || format!("Invalid match in state file '{}' line {}", path, number + 1);
+                let ((history1, flipped1), (history2, flipped2)) = decode_moves(&state.moves1).zip(decode_moves(&state.moves2)).ok_or_else(invalid)?;
+                if history1.len() != history2.len() || state.player1 >= experiment.players.len() || state.player2 >= experiment.players.len() {
+                    return Err(invalid());
+                }
+                let (total1, total2) = history1.iter().zip(&history2)
+                    .map(|(&move1, &move2)| payoffs.payoffs(move1, move2))
+                    .fold((0i64, 0i64), |(total1, total2), (payoff1, payoff2)| (total1 + payoff1 as i64, total2 + payoff2 as i64));
+                let summary = MatchSummary {
+                    repetition: state.repetition,
+                    player1: state.player1,
+                    player2: state.player2,
+                    result: MatchResult { history1, history2, total1, total2 },
+                };
+                completed.insert((state.repetition, state.player1, state.player2), CompletedMatch { summary, flipped1, flipped2 });
+            }
+            valid_len += line.len();
+        }
+
+        let file = OpenOptions::new().write(true).open(path)
+            .map_err(|e| format!("Failed to open state file '{}': {}", path, e))?;
+        file.set_len(valid_len as u64)
+            .map_err(|e| format!("Failed to write to state file '{}': {}", path, e))?;
+        let file = OpenOptions::new().append(true).open(path)
+            .map_err(|e| format!("Failed to open state file '{}': {}", path, e))?;
+        Ok(Self { writer: BufWriter::new(file), path: path.to_string(), completed })
+    }
+
+    // Number of completed matches still to be taken
+    pub fn completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    // The given match, if it was completed before
+    pub fn take_completed(&mut self, repetition: usize, player1: usize, player2: usize) -> Option<CompletedMatch> {
+        self.completed.remove(&(repetition, player1, player2))
+    }
+
+    // Records a completed match (`flipped` telling which moves of each turn noise flipped)
+    pub fn record(&mut self, summary: &MatchSummary, flipped: &[(bool, bool)]) -> Result<(), String> {
+        let (flipped1, flipped2): (Vec<bool>, Vec<bool>) = flipped.iter().copied().unzip();
+        let state = StateMatch {
+            repetition: summary.repetition,
+            player1: summary.player1,
+            player2: summary.player2,
+            moves1: encode_moves(&summary.result.history1, &flipped1),
+            moves2: encode_moves(&summary.result.history2, &flipped2),
+        };
+        self.write_line(&serde_json::to_string(&state).expect("a match state is valid JSON"))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| format!("Failed to write to state file '{}': {}", self.path, e))
+    }
+}
@@ -0,0 +1,310 @@
+// SQLite result store (`sqlite` feature): every run (a single match or a whole experiment) is appended to
+// the database, so that results accumulated over weeks can be queried later. Schema:
+//
+//     runs     one row per run: when, which command, the match settings and the resolved configuration
+//     players  the players of each run: display label, strategy name and parameters (as JSON)
+//     matches  one row per match: both players, repetition, scores, turns and cooperations of each player
+//     moves    (optional) the moves of every turn of a match, as 'C' or 'D'
+//
+// plus the `match_scores` view, with every match twice (once from the point of view of each player),
+// which is what most questions ("how does X do against Y?") are about.
+use crate::Move;
+use crate::experiment::{MatchSummary, Player};
+use crate::simulation::MatchConfig;
+use chrono::Local;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags};
+
+// Version of the schema below (kept in `PRAGMA user_version`)
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id            INTEGER PRIMARY KEY,
+    started_at    TEXT    NOT NULL,
+    command       TEXT    NOT NULL,
+    name          TEXT,
+    turns         INTEGER NOT NULL,
+    noise         REAL    NOT NULL,
+    seed          INTEGER,
+    repetitions   INTEGER NOT NULL,
+    length_known  INTEGER NOT NULL,
+    reward        INTEGER NOT NULL,
+    temptation    INTEGER NOT NULL,
+    sucker        INTEGER NOT NULL,
+    punishment    INTEGER NOT NULL,
+    configuration TEXT
+);
+CREATE TABLE IF NOT EXISTS players (
+    id       INTEGER PRIMARY KEY,
+    run_id   INTEGER NOT NULL REFERENCES runs(id),
+    position INTEGER NOT NULL,
+    label    TEXT    NOT NULL,
+    strategy TEXT    NOT NULL,
+    params   TEXT
+);
+CREATE TABLE IF NOT EXISTS matches (
+    id            INTEGER PRIMARY KEY,
+    run_id        INTEGER NOT NULL REFERENCES runs(id),
+    repetition    INTEGER NOT NULL,
+    player1_id    INTEGER NOT NULL REFERENCES players(id),
+    player2_id    INTEGER NOT NULL REFERENCES players(id),
+    score1        INTEGER NOT NULL,
+    score2        INTEGER NOT NULL,
+    turns         INTEGER NOT NULL,
+    cooperations1 INTEGER NOT NULL,
+    cooperations2 INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS moves (
+    match_id INTEGER NOT NULL REFERENCES matches(id),
+    turn     INTEGER NOT NULL,
+    move1    TEXT    NOT NULL,
+    move2    TEXT    NOT NULL,
+    PRIMARY KEY (match_id, turn)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS players_run ON players(run_id);
+CREATE INDEX IF NOT EXISTS matches_run ON matches(run_id);
+CREATE VIEW IF NOT EXISTS match_scores AS
+    SELECT id AS match_id, run_id, repetition, player1_id AS player_id, player2_id AS opponent_id,
+           score1 AS score, score2 AS opponent_score, turns, cooperations1 AS cooperations
+    FROM matches
+    UNION ALL
+    SELECT id, run_id, repetition, player2_id, player1_id, score2, score1, turns, cooperations2
+    FROM matches;
+";
+
+// A run to be recorded: the command that produced it, its settings and its players
+pub struct RunRecord<'a> {
+    // e.g. "match" or "run"
+    pub command: &'a str,
+    // Experiment name, if any
+    pub name: Option<&'a str>,
+    pub config: &'a MatchConfig,
+    pub seed: Option<u64>,
+    pub repetitions: usize,
+    // Resolved configuration (e.g. an experiment's TOML), for provenance
+    pub configuration: Option<&'a str>,
+    pub players: &'a [Player],
+}
+
+// Filter of `mean_score`: a player (label or strategy name), optionally an opponent, and a noise range
+#[derive(Default)]
+pub struct ScoreFilter {
+    pub player: String,
+    pub opponent: Option<String>,
+    // Only runs with noise strictly above / below these values
+    pub noise_above: Option<f64>,
+    pub noise_below: Option<f64>,
+}
+
+pub struct MeanScore {
+    pub matches: usize,
+    pub runs: usize,
+    // Mean score per match
+    pub score: f64,
+    pub opponent_score: f64,
+    // Mean payoff per turn
+    pub payoff_per_turn: f64,
+    pub cooperation_rate: f64,
+}
+
+// Rows of a query as text, with their column names
+pub struct QueryRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub struct ResultStore {
+    connection: Connection,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Database error: {}", e)
+}
+
+fn move_text(m: Move) -> &'static str {
+    match m {
+        Move::Cooperate => "C",
+        Move::Defect => "D",
+    }
+}
+
+fn cooperations(history: &[Move]) -> i64 {
+    history.iter().filter(|&&m| m == Move::Cooperate).count() as i64
+}
+
+impl ResultStore {
+    // Opens (or creates) the database, creating the tables if needed
+    pub fn open(path: &str) -> Result<Self, String> {
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Failed to open database '{}': {}", path, e))?;
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(db_error)?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "Database '{}' was created by a newer version of the simulator (schema version {}, supported {})",
+                path, version, SCHEMA_VERSION
+            ));
+        }
+        connection.execute_batch(SCHEMA).map_err(db_error)?;
+        connection.execute_batch(&format!("PRAGMA user_version = {}; PRAGMA foreign_keys = ON;", SCHEMA_VERSION)).map_err(db_error)?;
+        Ok(Self { connection })
+    }
+
+    // Opens an existing database without allowing any change (e.g. for arbitrary queries)
+    pub fn open_read_only(path: &str) -> Result<Self, String> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Failed to open database '{}': {}", path, e))?;
+        Ok(Self { connection })
+    }
+
+    // Records a run with all its matches (`player1`/`player2` of every match being indexes into `run.players`),
+    // in a single transaction. With `store_moves`, the moves of every turn are recorded too.
+    // Returns the id of the run.
+    pub fn record_run(&mut self, run: &RunRecord, matches: &[MatchSummary], store_moves: bool) -> Result<i64, String> {
+        let transaction = self.connection.transaction().map_err(db_error)?;
+        let payoffs = run.config.payoffs;
+        transaction.execute(
+            "INSERT INTO runs (started_at, command, name, turns, noise, seed, repetitions, length_known,
+                               reward, temptation, sucker, punishment, configuration)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                run.command,
+                run.name,
+                run.config.turns as i64,
+                run.config.noise,
+                // SQLite integers are signed: seeds above i64::MAX are stored as negative numbers
+                run.seed.map(|seed| seed as i64),
+                run.repetitions as i64,
+                run.config.length_known,
+                payoffs.reward,
+                payoffs.temptation,
+                payoffs.sucker,
+                payoffs.punishment,
+                run.configuration,
+            ],
+        ).map_err(db_error)?;
+        let run_id = transaction.last_insert_rowid();
+
+        let mut player_ids = Vec::with_capacity(run.players.len());
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO players (run_id, position, label, strategy, params) VALUES (?1, ?2, ?3, ?4, ?5)"
+            ).map_err(db_error)?;
+            for (position, player) in run.players.iter().enumerate() {
+                insert.execute(params![run_id, position as i64, player.label, player.strategy, player.params]).map_err(db_error)?;
+                player_ids.push(transaction.last_insert_rowid());
+            }
+        }
+
+        {
+            let mut insert_match = transaction.prepare(
+                "INSERT INTO matches (run_id, repetition, player1_id, player2_id, score1, score2, turns, cooperations1, cooperations2)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            ).map_err(db_error)?;
+            let mut insert_move = transaction.prepare(
+                "INSERT INTO moves (match_id, turn, move1, move2) VALUES (?1, ?2, ?3, ?4)"
+            ).map_err(db_error)?;
+            for summary in matches {
+                let player_id = |player: usize| player_ids.get(player).copied()
+                    .ok_or_else(|| format!("Match with unknown player {} (the run has {} players)", player, player_ids.len()));
+                let result = &summary.result;
+                insert_match.execute(params![
+                    run_id,
+                    summary.repetition as i64,
+                    player_id(summary.player1)?,
+                    player_id(summary.player2)?,
+                    result.total1,
+                    result.total2,
+                    result.turns() as i64,
+                    cooperations(&result.history1),
+                    cooperations(&result.history2),
+                ]).map_err(db_error)?;
+                let match_id = transaction.last_insert_rowid();
+                if store_moves {
+                    for (turn, (&move1, &move2)) in result.history1.iter().zip(&result.history2).enumerate() {
+                        insert_move.execute(params![match_id, turn as i64 + 1, move_text(move1), move_text(move2)]).map_err(db_error)?;
+                    }
+                }
+            }
+        }
+
+        transaction.commit().map_err(db_error)?;
+        Ok(run_id)
+    }
+
+    // Mean score of a player (matched by label or strategy name, ignoring case) in the matches selected by
+    // `filter`, over all runs. `None` if there is no such match.
+    pub fn mean_score(&self, filter: &ScoreFilter) -> Result<Option<MeanScore>, String> {
+        self.connection.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT s.run_id), AVG(s.score), AVG(s.opponent_score),
+                    SUM(s.score) * 1.0 / SUM(s.turns), SUM(s.cooperations) * 1.0 / SUM(s.turns)
+             FROM match_scores s
+             JOIN players p ON p.id = s.player_id
+             JOIN players o ON o.id = s.opponent_id
+             JOIN runs r ON r.id = s.run_id
+             WHERE (p.label = ?1 COLLATE NOCASE OR p.strategy = ?1 COLLATE NOCASE)
+               AND (?2 IS NULL OR o.label = ?2 COLLATE NOCASE OR o.strategy = ?2 COLLATE NOCASE)
+               AND (?3 IS NULL OR r.noise > ?3)
+               AND (?4 IS NULL OR r.noise < ?4)",
+            params![filter.player, filter.opponent, filter.noise_above, filter.noise_below],
+            |row| {
+                let matches: i64 = row.get(0)?;
+                if matches == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(MeanScore {
+                    matches: matches as usize,
+                    runs: row.get::<_, i64>(1)? as usize,
+                    score: row.get(2)?,
+                    opponent_score: row.get(3)?,
+                    payoff_per_turn: row.get(4)?,
+                    cooperation_rate: row.get(5)?,
+                }))
+            },
+        ).map_err(db_error)
+    }
+
+    // Latest `limit` runs: id, date, command, name, turns, noise, players and matches
+    pub fn runs(&self, limit: usize) -> Result<QueryRows, String> {
+        self.query(&format!(
+            "SELECT r.id, r.started_at, r.command, r.name, r.turns, r.noise,
+                    (SELECT COUNT(*) FROM players p WHERE p.run_id = r.id) AS players,
+                    (SELECT COUNT(*) FROM matches m WHERE m.run_id = r.id) AS matches
+             FROM runs r ORDER BY r.id DESC LIMIT {}",
+            limit
+        ))
+    }
+
+    // Every player label with its number of matches and mean payoff per turn over all runs, best first
+    pub fn standings(&self) -> Result<QueryRows, String> {
+        self.query(
+            "SELECT p.label, COUNT(*) AS matches, ROUND(SUM(s.score) * 1.0 / SUM(s.turns), 4) AS payoff_per_turn,
+                    ROUND(SUM(s.cooperations) * 1.0 / SUM(s.turns), 4) AS cooperation_rate
+             FROM match_scores s JOIN players p ON p.id = s.player_id
+             GROUP BY p.label ORDER BY payoff_per_turn DESC"
+        )
+    }
+
+    // Runs any SQL query, returning every value as text (NULL as an empty string)
+    pub fn query(&self, sql: &str) -> Result<QueryRows, String> {
+        let mut statement = self.connection.prepare(sql).map_err(db_error)?;
+        let columns: Vec<String> = statement.column_names().into_iter().map(String::from).collect();
+        let column_count = columns.len();
+        let rows = statement.query_map([], |row| {
+            (0..column_count)
+                .map(|i| row.get_ref(i).map(|value| match value {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(integer) => integer.to_string(),
+                    ValueRef::Real(real) => real.to_string(),
+                    ValueRef::Text(text) => String::from_utf8_lossy(text).to_string(),
+                    ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+                }))
+                .collect::<Result<Vec<String>, _>>()
+        }).map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        Ok(QueryRows { columns, rows })
+    }
+
+}
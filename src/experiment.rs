@@ -0,0 +1,496 @@
+// Declarative experiments: a single TOML file declares the players (with their parameters and
+// display labels), the match settings (turns, payoffs, noise, seed, repetitions) and the outputs.
+// Two players play each other; with more, every pair of players plays (round robin).
+//
+//     name = "tft-vs-the-world"
+//     turns = 200
+//     noise = 0.01
+//     seed = 42
+//     repetitions = 5
+//
+//     [payoffs]
+//     reward = 3
+//     temptation = 5
+//     sucker = 0
+//     punishment = 1
+//
+//     [[players]]
+//     strategy = "Tit for Tat"
+//
+//     [[players]]
+//     label = "Generous (0.3)"
+//     strategy = "Configurable Generous Tit for Tat"
+//     params = { cooperation_probability = 0.3 }
+//
+//     [output]
+//     raw_scores_folder = "results"
+//     summary_file = "results/summary.csv"
+//     plot_dir = "results/plots"
+//     parquet_dir = "results/parquet"
+use crate::{PayoffMatrix, Strategy, seed_rng};
+use crate::checkpoint::Checkpoint;
+#[cfg(feature = "parquet")]
+use crate::parquet_export::{MatchInfo, ParquetExport};
+use crate::progress::Progress;
+use crate::plot::{payoff_heatmap, write_match_plots, write_svg};
+use crate::results::{create_named_csv_file, datetime, write_comment_lines, write_csv_line};
+use crate::simulation::{MatchConfig, MatchResult, Turn, play_match_while};
+use crate::strategies::create_strategy_by_name;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use toml::Spanned;
+
+pub const SUMMARY_HEADER: &str = "Repetition,Player 1,Player 2,Score 1,Score 2,Average 1,Average 2";
+
+// The file as written by the user (spans are kept to point validation errors to their line)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExperimentFile {
+    name: Option<String>,
+    turns: Spanned<i64>,
+    noise: Option<Spanned<f64>>,
+    seed: Option<u64>,
+    repetitions: Option<Spanned<i64>>,
+    length_known: Option<bool>,
+    payoffs: Option<PayoffsFile>,
+    players: Spanned<Vec<PlayerFile>>,
+    #[serde(default)]
+    output: OutputConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PayoffsFile {
+    reward: i32,
+    temptation: i32,
+    sucker: i32,
+    punishment: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlayerFile {
+    strategy: Spanned<String>,
+    label: Option<Spanned<String>>,
+    params: Option<Spanned<toml::Value>>,
+    params_file: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    // One raw scores CSV per match
+    pub raw_scores_folder: Option<String>,
+    // One CSV line per match with its scores
+    pub summary_file: Option<String>,
+    // SVG charts of every match, and a heatmap of the payoffs of every matchup
+    pub plot_dir: Option<String>,
+    // Parquet files with every turn and every match (needs the `parquet` feature)
+    pub parquet_dir: Option<String>,
+}
+
+// A validated experiment, with every default resolved
+#[derive(Serialize, Clone, Debug)]
+pub struct Experiment {
+    pub name: String,
+    pub turns: usize,
+    pub noise: f64,
+    pub seed: Option<u64>,
+    pub repetitions: usize,
+    pub length_known: bool,
+    pub payoffs: PayoffsConfig,
+    pub players: Vec<Player>,
+    pub output: OutputConfig,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct PayoffsConfig {
+    pub reward: i32,
+    pub temptation: i32,
+    pub sucker: i32,
+    pub punishment: i32,
+}
+
+impl From<PayoffsConfig> for PayoffMatrix {
+    fn from(p: PayoffsConfig) -> Self {
+        PayoffMatrix { reward: p.reward, temptation: p.temptation, sucker: p.sucker, punishment: p.punishment }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Player {
+    pub label: String,
+    pub strategy: String,
+    // Parameters as JSON (whether inline or from a file), as `set_parameters` expects them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+}
+
+impl Player {
+    pub fn create_strategy(&self) -> Result<Box<dyn Strategy>, String> {
+        let params = match &self.params {
+            Some(json) => Some(serde_json::from_str(json).map_err(|e| format!("Invalid parameters of '{}': {}", self.label, e))?),
+            None => None,
+        };
+        create_strategy_by_name(&self.strategy, params)
+    }
+}
+
+pub struct MatchSummary {
+    pub repetition: usize,
+    pub player1: usize,
+    pub player2: usize,
+    pub result: MatchResult,
+}
+
+fn line_of(source: &str, span: Range<usize>) -> usize {
+    source[..span.start.min(source.len())].matches('\n').count() + 1
+}
+
+impl Experiment {
+    // Reads and validates an experiment file. Errors point to the offending line
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read experiment '{}': {}", path, e))?;
+        Self::parse(&source, path)
+    }
+
+    // `origin` (e.g. the file name) prefixes the errors. Like everywhere else, relative paths (e.g. `params_file`)
+    // are relative to the current directory
+    pub fn parse(source: &str, origin: &str) -> Result<Self, String> {
+        let error_at = |span: Range<usize>, message: String| format!("{}:{}: {}", origin, line_of(source, span), message);
+        let file: ExperimentFile = toml::from_str(source).map_err(|e| match e.span() {
+            Some(span) => error_at(span, e.message().to_string()),
+            None => format!("{}: {}", origin, e.message()),
+        })?;
+
+        if cfg!(not(feature = "parquet")) && file.output.parquet_dir.is_some() {
+            return Err(format!("{}: 'parquet_dir' needs the simulator to be built with the 'parquet' feature", origin));
+        }
+        let turns = usize::try_from(*file.turns.get_ref()).ok().filter(|&turns| turns > 0)
+            .ok_or_else(|| error_at(file.turns.span(), "'turns' should be > 0".to_string()))?;
+        let noise = match &file.noise {
+            Some(noise) if !(0.0..=1.0).contains(noise.get_ref()) =>
+                return Err(error_at(noise.span(), "'noise' should be between 0 and 1".to_string())),
+            Some(noise) => *noise.get_ref(),
+            None => 0.0,
+        };
+        let repetitions = match &file.repetitions {
+            Some(repetitions) => usize::try_from(*repetitions.get_ref()).ok().filter(|&r| r > 0)
+                .ok_or_else(|| error_at(repetitions.span(), "'repetitions' should be > 0".to_string()))?,
+            None => 1,
+        };
+        let payoffs = match file.payoffs {
+            Some(p) => PayoffsConfig { reward: p.reward, temptation: p.temptation, sucker: p.sucker, punishment: p.punishment },
+            None => {
+                let p = PayoffMatrix::default();
+                PayoffsConfig { reward: p.reward, temptation: p.temptation, sucker: p.sucker, punishment: p.punishment }
+            }
+        };
+        if file.players.get_ref().len() < 2 {
+            return Err(error_at(file.players.span(), "At least 2 [[players]] are needed".to_string()));
+        }
+
+        let mut players: Vec<Player> = Vec::new();
+        for player in file.players.get_ref() {
+            let params = match (&player.params, &player.params_file) {
+                (Some(_), Some(params_file)) => return Err(error_at(params_file.span(), "Use either 'params' or 'params_file', not both".to_string())),
+                // A string is taken as JSON (that is how the resolved configuration echoes them)
+                (Some(params), None) => match params.get_ref() {
+                    toml::Value::String(json) => Some(serde_json::from_str::<serde_json::Value>(json)
+                        .map_err(|e| error_at(params.span(), format!("Invalid 'params' JSON: {}", e)))?
+                        .to_string()),
+                    value => Some(serde_json::to_string(value)
+                        .map_err(|e| error_at(params.span(), format!("Invalid 'params': {}", e)))?),
+                },
+                (None, Some(params_file)) => {
+                    let params_path = params_file.get_ref();
+                    let contents = std::fs::read_to_string(params_path)
+                        .map_err(|e| error_at(params_file.span(), format!("Failed to read '{}': {}", params_path, e)))?;
+                    let json: serde_json::Value = serde_json::from_str(&contents)
+                        .map_err(|e| error_at(params_file.span(), format!("Failed to parse '{}': {}", params_path, e)))?;
+                    Some(json.to_string())
+                }
+                (None, None) => None,
+            };
+            // Creating the strategy checks both its name and its parameters
+            create_strategy_by_name(player.strategy.get_ref(), None)
+                .map_err(|e| error_at(player.strategy.span(), e))?;
+            let params_span = player.params.as_ref().map(Spanned::span)
+                .or(player.params_file.as_ref().map(Spanned::span))
+                .unwrap_or(player.strategy.span());
+            let mut resolved = Player { label: String::new(), strategy: player.strategy.get_ref().clone(), params };
+            let strategy = resolved.create_strategy()
+                .map_err(|e| error_at(params_span, e))?;
+            resolved.label = match &player.label {
+                Some(label) if players.iter().any(|p| &p.label == label.get_ref()) =>
+                    return Err(error_at(label.span(), format!("There is already a player labelled '{}'", label.get_ref()))),
+                Some(label) => label.get_ref().clone(),
+                None if players.iter().any(|p| p.label == strategy.to_string()) =>
+                    return Err(error_at(player.strategy.span(), format!("There is already a player called '{}', give this one a 'label'", strategy))),
+                None => strategy.to_string(),
+            };
+            players.push(resolved);
+        }
+
+        Ok(Self {
+            name: file.name.unwrap_or_else(|| {
+                Path::new(origin).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| "experiment".to_string())
+            }),
+            turns,
+            noise,
+            seed: file.seed,
+            repetitions,
+            length_known: file.length_known.unwrap_or(true),
+            payoffs,
+            players,
+            output: file.output,
+        })
+    }
+
+    // The resolved configuration, as TOML (echoed into every result file for provenance)
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("an experiment can always be written as TOML")
+    }
+
+    pub fn match_config(&self) -> MatchConfig {
+        let mut config = MatchConfig::new(self.turns)
+            .with_noise(self.noise)
+            .with_length_known(self.length_known);
+        config.payoffs = self.payoffs.into();
+        config
+    }
+
+    // Pairs of players (indexes) that play each other: every pair, in order
+    pub fn matchups(&self) -> Vec<(usize, usize)> {
+        (0..self.players.len())
+            .flat_map(|i| (i + 1..self.players.len()).map(move |j| (i, j)))
+            .collect()
+    }
+
+    // Average payoff per turn of each player (row) against each other player (column) over all the
+    // repetitions, `None` for the pairs that did not play
+    pub fn average_payoffs(&self, summaries: &[MatchSummary]) -> Vec<Vec<Option<f64>>> {
+        let players = self.players.len();
+        let mut totals = vec![vec![(0i64, 0usize); players]; players]; // (total score, turns played)
+        for summary in summaries {
+            let (player1, player2) = (summary.player1, summary.player2);
+            totals[player1][player2].0 += summary.result.total1;
+            totals[player1][player2].1 += summary.result.turns();
+            totals[player2][player1].0 += summary.result.total2;
+            totals[player2][player1].1 += summary.result.turns();
+        }
+        totals.into_iter()
+            .map(|row| row.into_iter()
+                .map(|(total, turns)| (turns > 0).then(|| total as f64 / turns as f64))
+                .collect())
+            .collect()
+    }
+
+    // Seed of every matchup (in `matchups` order) of the given repetition (starting at 0): drawn from a generator
+    // seeded with `seed + repetition`, so that any match can be replayed (or skipped) on its own
+    fn match_seeds(&self, repetition: usize) -> Vec<Option<u64>> {
+        let matchups = self.matchups().len();
+        match self.seed {
+            Some(seed) => {
+                let mut seeds = StdRng::seed_from_u64(seed.wrapping_add(repetition as u64));
+                (0..matchups).map(|_| Some(seeds.next_u64())).collect()
+            }
+            None => vec![None; matchups],
+        }
+    }
+
+    // Plays every matchup `repetitions` times, writing the configured outputs, and calls `on_match`
+    // after each match. Every match starts with freshly created strategies. With a seed, every match has its
+    // own seed (see `match_seeds`), so that the whole experiment is reproducible.
+    pub fn run<F: FnMut(&MatchSummary)>(&self, on_match: F) -> Result<Vec<MatchSummary>, String> {
+        self.run_with(RunControl::default(), on_match)
+    }
+
+    // Same as `run`, but checkpointed and/or interruptible (see `RunControl`). Matches completed before
+    // (according to the checkpoint) are not played again, and the outputs are the same as if they had been
+    // played now: with a seed, a resumed run gives the same results as an uninterrupted one.
+    pub fn run_with<F: FnMut(&MatchSummary)>(&self, control: RunControl, on_match: F) -> Result<Vec<MatchSummary>, String> {
+        let result = self.play_all(control, on_match);
+        seed_rng(None); // Back to unseeded random numbers, even after an error
+        result
+    }
+
+    fn play_all<F: FnMut(&MatchSummary)>(&self, control: RunControl, mut on_match: F) -> Result<Vec<MatchSummary>, String> {
+        let RunControl { mut checkpoint, interrupted, mut progress } = control;
+        let is_interrupted = || interrupted.is_some_and(|flag| flag.load(Ordering::SeqCst));
+        let started = datetime();
+        let provenance = format!("Experiment run on {}\n{}", started, self.to_toml());
+        let mut summary_writer = match &self.output.summary_file {
+            Some(summary_file) => Some(create_summary_file(summary_file, &provenance)?),
+            None => None,
+        };
+        #[cfg(feature = "parquet")]
+        let mut parquet = match &self.output.parquet_dir {
+            Some(folder) => Some(ParquetExport::create(folder, &format!("{}_{}", started, self.name))?),
+            None => None,
+        };
+
+        let config = self.match_config();
+        let total_matches = self.repetitions * self.matchups().len();
+        let mut summaries = Vec::new();
+        'repetitions: for repetition in 0..self.repetitions {
+            let seeds = self.match_seeds(repetition);
+            for (index, (player1, player2)) in self.matchups().into_iter().enumerate() {
+                let (label1, label2) = (&self.players[player1].label, &self.players[player2].label);
+                #[cfg(feature = "parquet")]
+                let info = MatchInfo { repetition: repetition + 1, seed: seeds[index], noise: self.noise, player1: label1, player2: label2 };
+
+                let completed = checkpoint.as_mut().and_then(|checkpoint| checkpoint.take_completed(repetition + 1, player1, player2));
+                let summary = if let Some(completed) = completed {
+                    // Its raw scores and plots were written when it was played
+                    #[cfg(feature = "parquet")]
+                    if let Some(export) = parquet.as_mut() {
+                        for turn in completed.turns(self) {
+                            export.add_turn(&info, &turn)?;
+                        }
+                    }
+                    if let Some(progress) = progress.as_deref_mut() {
+                        progress.skip_match(completed.summary.result.turns());
+                    }
+                    completed.summary
+                } else {
+                    if is_interrupted() {
+                        break 'repetitions;
+                    }
+                    seed_rng(seeds[index]);
+                    let mut strategy1 = self.players[player1].create_strategy()?;
+                    let mut strategy2 = self.players[player2].create_strategy()?;
+
+                    let (mut csv_writer, csv_path) = match &self.output.raw_scores_folder {
+                        Some(folder) => {
+                            let filename = format!("{}_{}_rep{}_({} vs {}).csv", datetime(), self.name, repetition + 1, label1, label2);
+                            let (file, path) = create_named_csv_file(folder, &filename, &provenance)?;
+                            (Some(BufWriter::new(file)), Some(path))
+                        }
+                        None => (None, None),
+                    };
+                    let mut write_error = None;
+                    #[cfg(feature = "parquet")]
+                    let mut export_error = None;
+                    let mut flipped = Vec::new();
+                    let on_turn = |turn: &Turn| {
+                        if let Some(Err(e)) = csv_writer.as_mut().map(|writer| write_csv_line(writer, turn, label1, label2)) {
+                            write_error.get_or_insert(e);
+                        }
+                        #[cfg(feature = "parquet")]
+                        if let Some(Err(e)) = parquet.as_mut().map(|export| export.add_turn(&info, turn)) {
+                            export_error.get_or_insert(e);
+                        }
+                        if checkpoint.is_some() {
+                            flipped.push((turn.flipped1, turn.flipped2));
+                        }
+                        if let Some(progress) = progress.as_deref_mut() {
+                            progress.add_turn();
+                        }
+                    };
+                    let result = play_match_while(strategy1.as_mut(), strategy2.as_mut(), &config, on_turn, || !is_interrupted())?;
+                    if let Some(e) = write_error {
+                        return Err(format!("Failed to write to CSV: {}", e));
+                    }
+                    #[cfg(feature = "parquet")]
+                    if let Some(e) = export_error {
+                        return Err(e);
+                    }
+                    if let Some(mut writer) = csv_writer {
+                        writer.flush().map_err(|e| format!("Failed to write to CSV: {}", e))?;
+                    }
+                    // A match cut by an interruption is discarded (it will be played again when resuming)
+                    if result.turns() < config.turns {
+                        if let Some(path) = csv_path {
+                            let _ = std::fs::remove_file(path);
+                        }
+                        break 'repetitions;
+                    }
+
+                    if let Some(folder) = &self.output.plot_dir {
+                        let prefix = format!("{}_{}_rep{}_({} vs {})", started, self.name, repetition + 1, label1, label2);
+                        write_match_plots(folder, &prefix, label1, label2, &result, &config.payoffs)?;
+                    }
+                    let summary = MatchSummary { repetition: repetition + 1, player1, player2, result };
+                    if let Some(checkpoint) = checkpoint.as_mut() {
+                        checkpoint.record(&summary, &flipped)?;
+                    }
+                    summary
+                };
+
+                #[cfg(feature = "parquet")]
+                if let Some(export) = parquet.as_mut() {
+                    export.add_match(&info, &summary.result)?;
+                }
+                if let Some(writer) = summary_writer.as_mut() {
+                    let (average1, average2) = summary.result.average_payoffs();
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{:.4},{:.4}",
+                        summary.repetition, label1, label2, summary.result.total1, summary.result.total2, average1, average2
+                    ).map_err(|e| format!("Failed to write the summary: {}", e))?;
+                }
+                if let Some(progress) = progress.as_deref_mut() {
+                    progress.clear();
+                }
+                on_match(&summary);
+                if let Some(progress) = progress.as_deref_mut() {
+                    progress.add_match();
+                }
+                summaries.push(summary);
+            }
+        }
+
+        // Whatever was completed is flushed, even when interrupted
+        if let Some(mut writer) = summary_writer {
+            writer.flush().map_err(|e| format!("Failed to write the summary: {}", e))?;
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(export) = parquet {
+            export.finish()?;
+        }
+        if summaries.len() < total_matches {
+            return Err(format!("Interrupted after {} of {} matches", summaries.len(), total_matches));
+        }
+        if let Some(folder) = &self.output.plot_dir {
+            let labels: Vec<String> = self.players.iter().map(|player| player.label.clone()).collect();
+            let payoffs = config.payoffs;
+            let values = [payoffs.reward, payoffs.temptation, payoffs.sucker, payoffs.punishment].map(f64::from);
+            let (min, max) = (values.into_iter().fold(f64::INFINITY, f64::min), values.into_iter().fold(f64::NEG_INFINITY, f64::max));
+            let heatmap = payoff_heatmap(&labels, &self.average_payoffs(&summaries), min, max);
+            write_svg(folder, &format!("{}_{}_payoffs.svg", started, self.name), &heatmap)?;
+        }
+        Ok(summaries)
+    }
+}
+
+// How a run is checkpointed and interrupted (by default, neither)
+#[derive(Default)]
+pub struct RunControl<'a> {
+    // Completed matches are recorded in it as they end, and the matches it already has are not played again
+    pub checkpoint: Option<Checkpoint>,
+    // Set (e.g. by a Ctrl-C handler) to stop the run: the match being played is discarded, the outputs of
+    // the completed ones are flushed, and the run returns an error
+    pub interrupted: Option<&'a AtomicBool>,
+    // Updated after every turn (and cleared before calling `on_match`, which may print)
+    pub progress: Option<&'a mut Progress>,
+}
+
+fn create_summary_file(path: &str, provenance: &str) -> Result<BufWriter<File>, String> {
+    if let Some(folder) = Path::new(path).parent().filter(|folder| !folder.as_os_str().is_empty()) {
+        std::fs::create_dir_all(folder)
+            .map_err(|e| format!("Failed to create directory '{}': {}", folder.display(), e))?;
+    }
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| format!("Failed to create file '{}': {}", path, e))?);
+    write_comment_lines(&mut writer, provenance)
+        .and_then(|_| writeln!(writer, "{}", SUMMARY_HEADER))
+        .map_err(|e| format!("Failed to write the summary: {}", e))?;
+    Ok(writer)
+}
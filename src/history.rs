@@ -0,0 +1,499 @@
+// Compact binary match histories (`.ipdh` files), for matches too long to be written as one CSV line per turn.
+// Layout (integers are little endian):
+//
+//     "IPDH"  version (u8)  3 reserved bytes  header length (u32)  header (JSON, see `HistoryHeader`)
+//     moves   2 bits per turn, 4 turns per byte (first turn in the lowest bits): bit 0 set if player 1
+//             defected, bit 1 set if player 2 defected
+//     index   one entry every `BLOCK_TURNS` turns, plus one for the end of the match: the totals (scores and
+//             cooperations of both players) before that turn, as 4 x 8 bytes
+//     footer  turns (u64)  index offset (u64)  turns per index block (u32)  "IPDI"
+//
+// Moves are at a fixed position, so any range of turns can be read without reading the rest of the file, and
+// the index gives the cumulative scores at any turn after scanning at most one block.
+// `convert_history` converts between this format, raw scores CSV files and JSON.
+use crate::{Move, PayoffMatrix};
+use crate::results::{parse_move, write_comment_lines, write_csv_line, CSV_HEADER};
+use crate::simulation::Turn;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+pub const EXTENSION: &str = "ipdh";
+const MAGIC: &[u8; 4] = b"IPDH";
+const FOOTER_MAGIC: &[u8; 4] = b"IPDI";
+pub const VERSION: u8 = 1;
+// Turns between index entries
+pub const BLOCK_TURNS: u64 = 65536;
+const FOOTER_LEN: u64 = 24;
+const INDEX_ENTRY_LEN: u64 = 32;
+
+// What the file says about the match (strategies, settings and seed), stored as JSON
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryHeader {
+    pub strategy1: String,
+    pub strategy2: String,
+    pub payoffs: PayoffMatrix,
+    // Unknown (e.g. when converted from a CSV file) if `None`
+    #[serde(default)]
+    pub noise: Option<f64>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub length_known: Option<bool>,
+    // Number of the first turn in the file (not 1 when only a range of turns of a match was converted)
+    #[serde(default = "first_turn_default")]
+    pub first_turn: u64,
+    #[serde(default)]
+    pub created: Option<String>,
+}
+
+fn first_turn_default() -> u64 {
+    1
+}
+
+impl HistoryHeader {
+    pub fn new(strategy1: &str, strategy2: &str, payoffs: PayoffMatrix) -> Self {
+        Self {
+            strategy1: strategy1.to_string(),
+            strategy2: strategy2.to_string(),
+            payoffs,
+            noise: None,
+            seed: None,
+            length_known: None,
+            first_turn: 1,
+            created: None,
+        }
+    }
+}
+
+// Scores and cooperations of both players over some turns
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct HistoryTotals {
+    pub score1: i64,
+    pub score2: i64,
+    pub cooperations1: u64,
+    pub cooperations2: u64,
+}
+
+impl HistoryTotals {
+    fn add(&mut self, move1: Move, move2: Move, payoffs: &PayoffMatrix) {
+        let (payoff1, payoff2) = payoffs.payoffs(move1, move2);
+        self.score1 += payoff1 as i64;
+        self.score2 += payoff2 as i64;
+        self.cooperations1 += (move1 == Move::Cooperate) as u64;
+        self.cooperations2 += (move2 == Move::Cooperate) as u64;
+    }
+
+    fn to_bytes(self) -> [u8; INDEX_ENTRY_LEN as usize] {
+        let mut bytes = [0; INDEX_ENTRY_LEN as usize];
+        bytes[0..8].copy_from_slice(&self.score1.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.score2.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.cooperations1.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.cooperations2.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |i: usize| <[u8; 8]>::try_from(&bytes[8 * i..8 * (i + 1)]).expect("index entries have 4 words");
+        Self {
+            score1: i64::from_le_bytes(word(0)),
+            score2: i64::from_le_bytes(word(1)),
+            cooperations1: u64::from_le_bytes(word(2)),
+            cooperations2: u64::from_le_bytes(word(3)),
+        }
+    }
+}
+
+fn encode(move1: Move, move2: Move) -> u8 {
+    (move1 == Move::Defect) as u8 | ((move2 == Move::Defect) as u8) << 1
+}
+
+fn decode(bits: u8) -> (Move, Move) {
+    let to_move = |defect: bool| if defect { Move::Defect } else { Move::Cooperate };
+    (to_move(bits & 1 != 0), to_move(bits & 2 != 0))
+}
+
+// Writes a history turn by turn (nothing but the current byte and the index is kept in memory)
+pub struct HistoryWriter {
+    writer: BufWriter<File>,
+    path: String,
+    payoffs: PayoffMatrix,
+    turns: u64,
+    pending: u8,
+    totals: HistoryTotals,
+    index: Vec<HistoryTotals>,
+}
+
+impl HistoryWriter {
+    pub fn create(path: &str, header: &HistoryHeader) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create file '{}': {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+        let header_json = serde_json::to_vec(header).map_err(|e| format!("Failed to write the history header: {}", e))?;
+        let header_len = u32::try_from(header_json.len()).map_err(|_| "History header too long".to_string())?;
+        writer.write_all(MAGIC)
+            .and_then(|_| writer.write_all(&[VERSION, 0, 0, 0]))
+            .and_then(|_| writer.write_all(&header_len.to_le_bytes()))
+            .and_then(|_| writer.write_all(&header_json))
+            .map_err(|e| format!("Failed to write to '{}': {}", path, e))?;
+        Ok(Self {
+            writer,
+            path: path.to_string(),
+            payoffs: header.payoffs,
+            turns: 0,
+            pending: 0,
+            totals: HistoryTotals::default(),
+            index: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, move1: Move, move2: Move) -> Result<(), String> {
+        if self.turns.is_multiple_of(BLOCK_TURNS) {
+            self.index.push(self.totals);
+        }
+        self.pending |= encode(move1, move2) << (2 * (self.turns % 4));
+        self.totals.add(move1, move2, &self.payoffs);
+        self.turns += 1;
+        if self.turns.is_multiple_of(4) {
+            let byte = std::mem::take(&mut self.pending);
+            self.writer.write_all(&[byte]).map_err(|e| format!("Failed to write to '{}': {}", self.path, e))?;
+        }
+        Ok(())
+    }
+
+    // Writes the index and the footer. Returns the number of turns written.
+    pub fn finish(mut self) -> Result<u64, String> {
+        let path = self.path.clone();
+        let write_error = |e: std::io::Error| format!("Failed to write to '{}': {}", path, e);
+        if !self.turns.is_multiple_of(4) {
+            self.writer.write_all(&[self.pending]).map_err(write_error)?;
+        }
+        self.index.push(self.totals);
+        let index_offset = self.writer.stream_position().map_err(write_error)?;
+        for entry in &self.index {
+            self.writer.write_all(&entry.to_bytes()).map_err(write_error)?;
+        }
+        self.writer.write_all(&self.turns.to_le_bytes())
+            .and_then(|_| self.writer.write_all(&index_offset.to_le_bytes()))
+            .and_then(|_| self.writer.write_all(&(BLOCK_TURNS as u32).to_le_bytes()))
+            .and_then(|_| self.writer.write_all(FOOTER_MAGIC))
+            .and_then(|_| self.writer.flush())
+            .map_err(write_error)?;
+        Ok(self.turns)
+    }
+}
+
+// Reads any range of turns of a history without reading the rest of the file
+pub struct HistoryReader {
+    reader: BufReader<File>,
+    path: String,
+    header: HistoryHeader,
+    turns: u64,
+    moves_offset: u64,
+    block_turns: u64,
+    index: Vec<HistoryTotals>,
+}
+
+impl HistoryReader {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+        let mut reader = BufReader::new(file);
+        let invalid = |what: &str| format!("'{}' is not a valid binary history ({})", path, what);
+        let read_error = |e: std::io::Error| format!("Failed to read '{}': {}", path, e);
+
+        let mut start = [0u8; 12];
+        reader.read_exact(&mut start).map_err(|_| invalid("too short"))?;
+        if &start[0..4] != MAGIC {
+            return Err(invalid("wrong magic number"));
+        }
+        if start[4] != VERSION {
+            return Err(invalid(&format!("unsupported version {}", start[4])));
+        }
+        let header_len = u32::from_le_bytes(start[8..12].try_into().expect("4 bytes")) as u64;
+        let mut header_json = vec![0u8; header_len as usize];
+        reader.read_exact(&mut header_json).map_err(|_| invalid("truncated header"))?;
+        let header: HistoryHeader = serde_json::from_slice(&header_json)
+            .map_err(|e| invalid(&format!("invalid header: {}", e)))?;
+        let moves_offset = 12 + header_len;
+
+        let file_len = reader.seek(SeekFrom::End(0)).map_err(read_error)?;
+        if file_len < moves_offset + FOOTER_LEN {
+            return Err(invalid("missing footer, was the match interrupted?"));
+        }
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        reader.seek(SeekFrom::Start(file_len - FOOTER_LEN)).map_err(read_error)?;
+        reader.read_exact(&mut footer).map_err(read_error)?;
+        if &footer[20..24] != FOOTER_MAGIC {
+            return Err(invalid("missing footer, was the match interrupted?"));
+        }
+        let turns = u64::from_le_bytes(footer[0..8].try_into().expect("8 bytes"));
+        let index_offset = u64::from_le_bytes(footer[8..16].try_into().expect("8 bytes"));
+        let block_turns = u32::from_le_bytes(footer[16..20].try_into().expect("4 bytes")) as u64;
+        if block_turns == 0 {
+            return Err(invalid("inconsistent footer"));
+        }
+        let entries = turns.div_ceil(block_turns) + 1;
+        if index_offset != moves_offset + turns.div_ceil(4)
+            || index_offset + entries * INDEX_ENTRY_LEN + FOOTER_LEN != file_len
+        {
+            return Err(invalid("inconsistent footer"));
+        }
+
+        let mut index_bytes = vec![0u8; (entries * INDEX_ENTRY_LEN) as usize];
+        reader.seek(SeekFrom::Start(index_offset)).map_err(read_error)?;
+        reader.read_exact(&mut index_bytes).map_err(read_error)?;
+        let index = index_bytes.chunks(INDEX_ENTRY_LEN as usize).map(HistoryTotals::from_bytes).collect();
+
+        Ok(Self { reader, path: path.to_string(), header, turns, moves_offset, block_turns, index })
+    }
+
+    pub fn header(&self) -> &HistoryHeader {
+        &self.header
+    }
+
+    pub fn turns(&self) -> u64 {
+        self.turns
+    }
+
+    // Totals of the whole match
+    pub fn totals(&self) -> HistoryTotals {
+        *self.index.last().expect("the index always has an entry for the end")
+    }
+
+    // Moves of the turns in `range` (counted from 0, i.e. the first turn in the file is turn 0)
+    pub fn read_turns(&mut self, range: Range<u64>) -> Result<Vec<(Move, Move)>, String> {
+        if range.start > range.end || range.end > self.turns {
+            return Err(format!("Turns {}..{} out of range, the history has {} turns", range.start, range.end, self.turns));
+        }
+        let first_byte = range.start / 4;
+        let mut bytes = vec![0u8; (range.end.div_ceil(4) - first_byte) as usize];
+        self.reader.seek(SeekFrom::Start(self.moves_offset + first_byte))
+            .and_then(|_| self.reader.read_exact(&mut bytes))
+            .map_err(|e| format!("Failed to read '{}': {}", self.path, e))?;
+        Ok(range
+            .map(|turn| decode((bytes[(turn / 4 - first_byte) as usize] >> (2 * (turn % 4))) & 0b11))
+            .collect())
+    }
+
+    // Totals of the turns before `turn` (counted from 0): at most one index block is read
+    pub fn totals_before(&mut self, turn: u64) -> Result<HistoryTotals, String> {
+        if turn > self.turns {
+            return Err(format!("Turn {} out of range, the history has {} turns", turn, self.turns));
+        }
+        if turn == self.turns {
+            return Ok(self.totals());
+        }
+        let block = turn / self.block_turns;
+        let mut totals = self.index[block as usize];
+        let payoffs = self.header.payoffs;
+        for (move1, move2) in self.read_turns(block * self.block_turns..turn)? {
+            totals.add(move1, move2, &payoffs);
+        }
+        Ok(totals)
+    }
+
+    // Moves of both players over the whole match
+    pub fn read_all(&mut self) -> Result<(Vec<Move>, Vec<Move>), String> {
+        Ok(self.read_turns(0..self.turns)?.into_iter().unzip())
+    }
+}
+
+// A history in memory, as read from any of the supported formats
+pub struct History {
+    pub header: HistoryHeader,
+    pub history1: Vec<Move>,
+    pub history2: Vec<Move>,
+}
+
+// JSON format: the header, plus the moves of each player as strings of C/D
+#[derive(Serialize, Deserialize)]
+struct JsonHistory {
+    header: HistoryHeader,
+    moves1: String,
+    moves2: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HistoryFormat {
+    Binary,
+    Csv,
+    Json,
+}
+
+impl HistoryFormat {
+    // From the file extension: `.ipdh`, `.csv` or `.json`
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()).map(str::to_lowercase).as_deref() {
+            Some(EXTENSION) => Ok(HistoryFormat::Binary),
+            Some("csv") => Ok(HistoryFormat::Csv),
+            Some("json") => Ok(HistoryFormat::Json),
+            _ => Err(format!("Unknown history format of '{}', use a .{}, .csv or .json file", path, EXTENSION)),
+        }
+    }
+}
+
+fn move_char(m: Move) -> char {
+    match m {
+        Move::Cooperate => 'C',
+        Move::Defect => 'D',
+    }
+}
+
+fn parse_moves(moves: &str) -> Result<Vec<Move>, String> {
+    moves.chars()
+        .map(|c| match c {
+            'C' => Ok(Move::Cooperate),
+            'D' => Ok(Move::Defect),
+            _ => Err(format!("Invalid move '{}', must be C or D", c)),
+        })
+        .collect()
+}
+
+// Reads a raw scores CSV (as written with `--raw-scores-folder`). The payoffs are taken from the file, and the
+// noise, seed and whether the length was known from its `# name = value` comments, if any (as `convert` writes them).
+// Strategy names may contain ", " (e.g. "Win-Stay, Lose-Shift"), but the columns are separated by a bare ','.
+fn read_csv_history(path: &str) -> Result<History, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+    let mut history = History { header: HistoryHeader::new("", "", PayoffMatrix::default()), history1: Vec::new(), history2: Vec::new() };
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some((name, value)) = line.strip_prefix('#').and_then(|comment| comment.split_once('=')) {
+            let header = &mut history.header;
+            match name.trim() {
+                "noise" => header.noise = value.trim().parse().ok().or(header.noise),
+                "seed" => header.seed = value.trim().parse().ok().or(header.seed),
+                "length_known" => header.length_known = value.trim().parse().ok().or(header.length_known),
+                _ => {}
+            }
+        }
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Iteration,") {
+            continue;
+        }
+        let invalid = || format!("Invalid raw scores CSV line {}: '{}'", number + 1, line);
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 7 {
+            return Err(invalid());
+        }
+        let end = fields.len() - 4;
+        let (move1, move2) = parse_move(fields[end]).zip(parse_move(fields[end + 1])).ok_or_else(invalid)?;
+        let payoff1: i32 = fields[end + 2].trim().parse().map_err(|_| invalid())?;
+        let payoff2: i32 = fields[end + 3].trim().parse().map_err(|_| invalid())?;
+        if history.history1.is_empty() {
+            history.header.first_turn = fields[0].trim().parse().map_err(|_| invalid())?;
+            let names = fields[1..end].join(",");
+            let separators: Vec<usize> = names.match_indices(',')
+                .map(|(position, _)| position)
+                .filter(|&position| !names[position + 1..].starts_with(' '))
+                .collect();
+            let [separator] = separators[..] else {
+                return Err(format!("Cannot tell the strategy names apart in CSV line {}: '{}'", number + 1, line));
+            };
+            history.header.strategy1 = names[..separator].to_string();
+            history.header.strategy2 = names[separator + 1..].to_string();
+        }
+        let payoffs = &mut history.header.payoffs;
+        match (move1, move2) {
+            (Move::Cooperate, Move::Cooperate) => payoffs.reward = payoff1,
+            (Move::Cooperate, Move::Defect) => (payoffs.sucker, payoffs.temptation) = (payoff1, payoff2),
+            (Move::Defect, Move::Cooperate) => (payoffs.temptation, payoffs.sucker) = (payoff1, payoff2),
+            (Move::Defect, Move::Defect) => payoffs.punishment = payoff1,
+        }
+        history.history1.push(move1);
+        history.history2.push(move2);
+    }
+    Ok(history)
+}
+
+fn write_csv_history(path: &str, history: &History) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create file '{}': {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    let header = &history.header;
+    let mut comments = String::new();
+    if let Some(noise) = header.noise {
+        comments.push_str(&format!("noise = {}\n", noise));
+    }
+    if let Some(seed) = header.seed {
+        comments.push_str(&format!("seed = {}\n", seed));
+    }
+    if let Some(length_known) = header.length_known {
+        comments.push_str(&format!("length_known = {}\n", length_known));
+    }
+    let write_error = |e: std::io::Error| format!("Failed to write to '{}': {}", path, e);
+    write_comment_lines(&mut writer, &comments)
+        .and_then(|_| writeln!(writer, "{}", CSV_HEADER))
+        .map_err(write_error)?;
+    for (i, (&move1, &move2)) in history.history1.iter().zip(&history.history2).enumerate() {
+        let (payoff1, payoff2) = header.payoffs.payoffs(move1, move2);
+        // Whether a move was flipped by noise is not recorded
+        let turn = Turn { iteration: header.first_turn as usize + i, move1, move2, payoff1, payoff2, flipped1: false, flipped2: false };
+        write_csv_line(&mut writer, &turn, &header.strategy1, &header.strategy2).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+// Reads the turns in `range` (counted from 0, `None` for all of them) of a history in any supported format.
+// Binary histories only read the requested turns.
+pub fn read_history(path: &str, range: Option<Range<u64>>) -> Result<History, String> {
+    let mut history = match HistoryFormat::from_path(path)? {
+        HistoryFormat::Binary => {
+            let mut reader = HistoryReader::open(path)?;
+            let range = range.unwrap_or(0..reader.turns());
+            let mut header = reader.header().clone();
+            header.first_turn += range.start;
+            let (history1, history2) = reader.read_turns(range)?.into_iter().unzip();
+            return Ok(History { header, history1, history2 });
+        }
+        HistoryFormat::Csv => read_csv_history(path)?,
+        HistoryFormat::Json => {
+            let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+            let json: JsonHistory = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse '{}': {}", path, e))?;
+            let (history1, history2) = (parse_moves(&json.moves1)?, parse_moves(&json.moves2)?);
+            if history1.len() != history2.len() {
+                return Err(format!("Invalid history '{}': both players must have the same number of moves", path));
+            }
+            History { header: json.header, history1, history2 }
+        }
+    };
+    if let Some(range) = range {
+        let turns = history.history1.len() as u64;
+        if range.start > range.end || range.end > turns {
+            return Err(format!("Turns {}..{} out of range, the history has {} turns", range.start, range.end, turns));
+        }
+        history.header.first_turn += range.start;
+        history.history1 = history.history1[range.start as usize..range.end as usize].to_vec();
+        history.history2 = history.history2[range.start as usize..range.end as usize].to_vec();
+    }
+    Ok(history)
+}
+
+pub fn write_history(path: &str, history: &History) -> Result<(), String> {
+    match HistoryFormat::from_path(path)? {
+        HistoryFormat::Binary => {
+            let mut writer = HistoryWriter::create(path, &history.header)?;
+            for (&move1, &move2) in history.history1.iter().zip(&history.history2) {
+                writer.push(move1, move2)?;
+            }
+            writer.finish().map(|_| ())
+        }
+        HistoryFormat::Csv => write_csv_history(path, history),
+        HistoryFormat::Json => {
+            let json = JsonHistory {
+                header: history.header.clone(),
+                moves1: history.history1.iter().copied().map(move_char).collect(),
+                moves2: history.history2.iter().copied().map(move_char).collect(),
+            };
+            let contents = serde_json::to_string_pretty(&json).map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+            std::fs::write(path, contents).map_err(|e| format!("Failed to write '{}': {}", path, e))
+        }
+    }
+}
+
+// Converts a history between formats (chosen from the file extensions), keeping only the turns in
+// `range` (counted from 0) if given. Returns the number of turns written.
+pub fn convert_history(input: &str, output: &str, range: Option<Range<u64>>) -> Result<usize, String> {
+    HistoryFormat::from_path(output)?;
+    let history = read_history(input, range)?;
+    write_history(output, &history)?;
+    Ok(history.history1.len())
+}
@@ -0,0 +1,139 @@
+// Interactive sessions: a human plays against a strategy, one turn at a time
+use crate::{MatchContext, Move, PayoffMatrix, Strategy};
+use crate::simulation::{MatchResult, Turn};
+use std::io::{BufRead, Write};
+
+// Number of turns shown in the running history
+const HISTORY_WINDOW: usize = 30;
+
+pub type StrategyFactory<'a> = &'a dyn Fn() -> Result<Box<dyn Strategy>, String>;
+
+pub struct SessionConfig {
+    // `None` plays until the human quits
+    pub turns: Option<usize>,
+    // Hides the opponent's name until the end of the session
+    pub hidden_opponent: bool,
+}
+
+fn move_char(m: Move) -> char {
+    match m {
+        Move::Cooperate => 'C',
+        Move::Defect => 'D',
+    }
+}
+
+fn strip(history: &[Move]) -> String {
+    let start = history.len().saturating_sub(HISTORY_WINDOW);
+    let moves: String = history[start..].iter().map(|&m| move_char(m)).collect();
+    if start > 0 { format!("...{}", moves) } else { moves }
+}
+
+fn io_error(e: std::io::Error) -> String {
+    format!("Failed to interact with the terminal: {}", e)
+}
+
+fn context(config: &SessionConfig, turn: usize) -> MatchContext {
+    MatchContext {
+        total_turns: config.turns,
+        current_turn: turn,
+        payoffs: PayoffMatrix::default(),
+        noise: 0.0,
+    }
+}
+
+// Plays a session between the human (first player, reading moves from `input`) and the
+// strategy built by `create_opponent`. Undoing a turn rebuilds the opponent and replays the
+// remaining history, so that its internal state matches it again.
+pub fn play_session<R: BufRead, W: Write>(
+    create_opponent: StrategyFactory,
+    config: &SessionConfig,
+    input: &mut R,
+    output: &mut W,
+) -> Result<(MatchResult, String), String> {
+    let mut opponent = create_opponent()?;
+    let opponent_name = opponent.to_string();
+    let shown_name = if config.hidden_opponent { "Opponent".to_string() } else { opponent_name.clone() };
+
+    let mut human_history: Vec<Move> = Vec::new();
+    let mut opponent_history: Vec<Move> = Vec::new();
+    let mut payoffs: Vec<(i32, i32)> = Vec::new();
+
+    writeln!(output, "You are playing against: {}", shown_name).map_err(io_error)?;
+    writeln!(output, "Commands: 'c' cooperate, 'd' defect, 'u' undo the last turn, 'q' quit").map_err(io_error)?;
+
+    loop {
+        let turn = human_history.len() + 1;
+        if config.turns.is_some_and(|turns| turn > turns) {
+            break;
+        }
+        let total_human: i32 = payoffs.iter().map(|p| p.0).sum();
+        let total_opponent: i32 = payoffs.iter().map(|p| p.1).sum();
+        writeln!(output).map_err(io_error)?;
+        writeln!(output, "You:      {}", strip(&human_history)).map_err(io_error)?;
+        writeln!(output, "{:<9} {}", format!("{}:", if config.hidden_opponent { "Opponent" } else { "Strategy" }), strip(&opponent_history)).map_err(io_error)?;
+        writeln!(output, "Score: you {} - {} {}", total_human, total_opponent, shown_name).map_err(io_error)?;
+        match config.turns {
+            Some(turns) => write!(output, "Turn {}/{} [c/d/u/q]: ", turn, turns),
+            None => write!(output, "Turn {} [c/d/u/q]: ", turn),
+        }.map_err(io_error)?;
+        output.flush().map_err(io_error)?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line).map_err(io_error)? == 0 {
+            break; // End of input
+        }
+        let human_move = match line.trim().to_lowercase().as_str() {
+            "c" | "cooperate" => Move::Cooperate,
+            "d" | "defect" => Move::Defect,
+            "q" | "quit" => break,
+            "u" | "undo" => {
+                if human_history.is_empty() {
+                    writeln!(output, "Nothing to undo").map_err(io_error)?;
+                    continue;
+                }
+                human_history.pop();
+                opponent_history.pop();
+                payoffs.pop();
+                opponent = create_opponent()?;
+                for t in 0..opponent_history.len() {
+                    opponent.try_next_move(&opponent_history[..t], &human_history[..t], &context(config, t + 1))?;
+                }
+                writeln!(output, "Last turn undone").map_err(io_error)?;
+                continue;
+            }
+            other => {
+                writeln!(output, "Unknown command '{}'", other).map_err(io_error)?;
+                continue;
+            }
+        };
+
+        let opponent_move = opponent.try_next_move(&opponent_history, &human_history, &context(config, turn))?;
+        let (payoff_human, payoff_opponent) = PayoffMatrix::default().payoffs(human_move, opponent_move);
+        writeln!(
+            output,
+            "You: {:?}, {}: {:?} ; Payoffs: {:?}",
+            human_move, shown_name, opponent_move, (payoff_human, payoff_opponent)
+        ).map_err(io_error)?;
+        human_history.push(human_move);
+        opponent_history.push(opponent_move);
+        payoffs.push((payoff_human, payoff_opponent));
+    }
+
+    if config.hidden_opponent {
+        writeln!(output, "\nYou were playing against: {}", opponent_name).map_err(io_error)?;
+    }
+
+    let total1 = payoffs.iter().map(|p| p.0 as i64).sum();
+    let total2 = payoffs.iter().map(|p| p.1 as i64).sum();
+    Ok((MatchResult { history1: human_history, history2: opponent_history, total1, total2 }, opponent_name))
+}
+
+// Turn by turn view of a finished match (e.g. to write it to a raw scores CSV)
+pub fn turns(result: &MatchResult, payoffs: &PayoffMatrix) -> Vec<Turn> {
+    result.history1.iter().zip(&result.history2).enumerate()
+        .map(|(i, (&move1, &move2))| {
+            let (payoff1, payoff2) = payoffs.payoffs(move1, move2);
+            Turn { iteration: i + 1, move1, move2, payoff1, payoff2, flipped1: false, flipped2: false }
+        })
+        .collect()
+}
@@ -1,6 +1,25 @@
 pub mod strategies;
+pub mod simulation;
+pub mod analysis;
+pub mod results;
+pub mod interactive;
+pub mod experiment;
+pub mod checkpoint;
+pub mod progress;
+pub mod trace;
+pub mod report;
+pub mod plot;
+pub mod history;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+#[cfg(feature = "sqlite")]
+pub mod db;
 
+use std::cell::RefCell;
 use std::fmt;
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
 use serde_json;
 
 #[derive(Clone, Copy, PartialEq, Debug, Hash, Eq)]
@@ -9,6 +28,62 @@ pub enum Move {
     Defect
 }
 
+// Cooperation probabilities of a memory-one strategy: on the first turn,
+// and after each (own, opponent) outcome of the previous turn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MemoryOne {
+    pub first: f64,
+    pub cc: f64,
+    pub cd: f64,
+    pub dc: f64,
+    pub dd: f64,
+}
+
+// Payoff values of each outcome (for a Prisoner's Dilemma: TEMPTATION > REWARD > PUNISHMENT > SUCKER)
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PayoffMatrix {
+    pub reward: i32,
+    pub temptation: i32,
+    pub sucker: i32,
+    pub punishment: i32,
+}
+
+impl Default for PayoffMatrix {
+    fn default() -> Self {
+        Self { reward: REWARD, temptation: TEMPTATION, sucker: SUCKER, punishment: PUNISHMENT }
+    }
+}
+
+impl PayoffMatrix {
+    pub fn payoffs(&self, move1: Move, move2: Move) -> (i32, i32) {
+        match (move1, move2) {
+            (Move::Cooperate, Move::Cooperate) => (self.reward,     self.reward),
+            (Move::Cooperate, Move::Defect)    => (self.sucker,     self.temptation),
+            (Move::Defect,    Move::Cooperate) => (self.temptation, self.sucker),
+            (Move::Defect,    Move::Defect)    => (self.punishment, self.punishment),
+        }
+    }
+}
+
+// What a strategy can know about the match it is playing
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MatchContext {
+    // Total number of turns of the match, `None` if the length is unknown to the players
+    pub total_turns: Option<usize>,
+    // Turn being played (starting at 1)
+    pub current_turn: usize,
+    pub payoffs: PayoffMatrix,
+    // Probability of each move being flipped by mistake
+    pub noise: f64,
+}
+
+impl MatchContext {
+    // Number of turns left after the current one, if the match length is known
+    pub fn remaining_turns(&self) -> Option<usize> {
+        self.total_turns.map(|total| total.saturating_sub(self.current_turn))
+    }
+}
+
 // Defines the interface for any strategy
 //pub trait Strategy: fmt::Display {
 pub trait Strategy: fmt::Display + Send + Sync {
@@ -18,6 +93,20 @@ pub trait Strategy: fmt::Display + Send + Sync {
     // `opponent_history` contains the oponent's past moves (from oldest to newest)
     fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move;
 
+    // Same as `next_move`, but with information about the match itself (e.g. how many turns are left).
+    // Strategies that need the context override it, the rest simply keep implementing `next_move`.
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], _context: &MatchContext) -> Move {
+        self.next_move(own_history, opponent_history)
+    }
+
+    // Same as `next_move_with_context`, for strategies that can fail to decide their move (e.g. a recording
+    // that ran out, or an external program that crashed). This is what the match runner calls: an error stops
+    // the match. Strategies that cannot fail keep the default, and those that can also need to return a move
+    // from `next_move`, for callers that cannot handle errors.
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        Ok(self.next_move_with_context(own_history, opponent_history, context))
+    }
+
     // Reset needed?
     //fn reset(&mut self);
 
@@ -26,6 +115,18 @@ pub trait Strategy: fmt::Display + Send + Sync {
         // Default implementation does nothing
         Ok(())
     }
+
+    // Strategies that learn during a run can export their (learned) parameters, in the same
+    // format accepted by `set_parameters`, so that a later run can start from them
+    fn export_parameters(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    // Memory-one strategies can expose their cooperation probabilities so that
+    // their matchups can be solved analytically (see the `analysis` module)
+    fn memory_one(&self) -> Option<MemoryOne> {
+        None
+    }
 }
 
 pub const REWARD: i32 = 3;
@@ -40,4 +141,26 @@ pub fn calculate_payoffs(move1: Move, move2: Move) -> (i32, i32) {
         (Move::Defect,    Move::Cooperate) => (TEMPTATION, SUCKER),     // Temptation / Sucker's payoff
         (Move::Defect,    Move::Defect)    => (PUNISHMENT, PUNISHMENT), // Mutual defection
     }
-}
\ No newline at end of file
+}
+// Random numbers used by strategies and noise: the thread's RNG, unless a seed was set with `seed_rng`
+// (e.g. for an experiment), in which case runs on this thread are reproducible
+thread_local! {
+    static SEEDED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+// Seeds the random numbers of the current thread (`None` goes back to the unseeded thread RNG)
+pub fn seed_rng(seed: Option<u64>) {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = seed.map(StdRng::seed_from_u64));
+}
+
+pub fn with_rng<R>(f: impl FnOnce(&mut dyn RngCore) -> R) -> R {
+    SEEDED_RNG.with(|seeded| match seeded.borrow_mut().as_mut() {
+        Some(rng) => f(rng),
+        None => f(&mut rand::rng()),
+    })
+}
+
+// `true` with the given probability
+pub fn random_bool(probability: f64) -> bool {
+    with_rng(|rng| rng.random_bool(probability))
+}
@@ -1,25 +1,106 @@
-use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
-use iterated_prisoners_dilemma_lib::{Move, Strategy, calculate_payoffs};
+use iterated_prisoners_dilemma_lib::strategies::{create_strategy_by_name, find_runtime_strategy, find_strategy_info, runtime_strategies, StrategyInfo};
+use iterated_prisoners_dilemma_lib::strategies::script::{register_script, DEFAULT_MAX_OPERATIONS};
+use iterated_prisoners_dilemma_lib::strategies::transformers::{is_transformer_expression, TRANSFORMERS};
+use iterated_prisoners_dilemma_lib::strategies::wasm_plugin::{register_plugins_in_dir, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES};
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::analysis::exact_payoffs_for;
+use iterated_prisoners_dilemma_lib::results::{create_csv_file, datetime, write_csv_line};
+use iterated_prisoners_dilemma_lib::plot::write_match_plots;
+use iterated_prisoners_dilemma_lib::history::{convert_history, read_history, HistoryFormat, HistoryHeader, HistoryReader, HistoryWriter};
+use iterated_prisoners_dilemma_lib::report::{write_html_report, ReportInfo};
+use iterated_prisoners_dilemma_lib::experiment::{Experiment, RunControl};
+use iterated_prisoners_dilemma_lib::checkpoint::Checkpoint;
+use iterated_prisoners_dilemma_lib::progress::{Progress, ProgressStyle};
+use iterated_prisoners_dilemma_lib::trace::{set_tracing, take_reasons, Reason};
+#[cfg(feature = "sqlite")]
+use iterated_prisoners_dilemma_lib::experiment::{MatchSummary, Player};
+#[cfg(feature = "parquet")]
+use iterated_prisoners_dilemma_lib::parquet_export::{MatchInfo, ParquetExport};
+#[cfg(feature = "sqlite")]
+use iterated_prisoners_dilemma_lib::db::{QueryRows, ResultStore, RunRecord, ScoreFilter};
+use iterated_prisoners_dilemma_lib::interactive::{play_session, turns, SessionConfig};
+use iterated_prisoners_dilemma_lib::{PayoffMatrix, Strategy};
 
-use clap::Parser;
-use std::fs::{create_dir_all, File};
-use std::io::Write;
+use clap::{Args as ClapArgs, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
 use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
-use chrono::Local;
 use serde_json;
 
+// Exit code when a command fails while running (e.g. an unknown strategy, invalid parameters or an I/O error).
+// Invalid command lines (e.g. a missing required argument) exit with 2, as reported by clap.
+const EXIT_RUNTIME_ERROR: u8 = 1;
+// Exit code when a run is stopped with Ctrl-C (128 + SIGINT, as shells report it)
+const EXIT_INTERRUPTED: u8 = 130;
+
+// Set by the Ctrl-C handler of the 'run' command
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = "Simulates the Iterated Prisoner's Dilemma between two strategies.")]
+#[command(
+    author,
+    version,
+    about,
+    long_about = "Simulates the Iterated Prisoner's Dilemma between strategies.",
+    after_help = "Exit codes: 0 on success, 1 when the command fails while running, 2 when the command line is invalid, 130 when a run is interrupted with Ctrl-C."
+)]
 struct Args {
-    #[arg(long, help = "Number of iterations to run for the simulation")]
-    iterations: Option<u32>,
+    #[command(subcommand)]
+    command: Command,
+
+    #[arg(long, global = true, value_name = "NAME=FILE", value_parser = parse_script, help = "Registers the Rhai script FILE (defining 'next_move(own, opp)') as a strategy called NAME. Can be repeated")]
+    script: Vec<(String, String)>,
+
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_OPERATIONS, help = "Maximum number of operations a script strategy can run to decide each move")]
+    script_max_operations: u64,
+
+    #[arg(long, global = true, help = "Folder with WebAssembly strategy plugins (.wasm or .wat files), each registered as a strategy named after its file")]
+    plugin_dir: Option<String>,
+
+    #[arg(long, global = true, default_value_t = DEFAULT_FUEL, help = "Fuel (roughly, instructions) a plugin strategy can use on each call")]
+    plugin_fuel: u64,
+
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_MEMORY_BYTES, help = "Maximum memory (in bytes) of each plugin strategy")]
+    plugin_max_memory: usize,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Simulate a match between two strategies")]
+    Match(MatchArgs),
+    #[command(about = "List all available strategies and transformers")]
+    List,
+    #[command(about = "Describe a strategy (or transformer expression): aliases, parameters and behaviour")]
+    Describe(DescribeArgs),
+    #[command(about = "Check that a JSON parameters file is valid for a strategy")]
+    ValidateParams(ValidateParamsArgs),
+    #[command(about = "Play interactively against a strategy, choosing your move (C/D) every turn")]
+    Play(PlayArgs),
+    #[command(about = "Run the experiment declared in a TOML file (players, turns, payoffs, noise, seed, repetitions and outputs)")]
+    Run(RunArgs),
+    #[cfg(feature = "sqlite")]
+    #[command(about = "Answer common questions about the runs recorded in a SQLite database (with --db)")]
+    Query(QueryArgs),
+    #[command(about = "Convert a match history between the binary (.ipdh), CSV and JSON formats (chosen from the file extensions)")]
+    Convert(ConvertArgs),
+    #[command(about = "Print the shell completion script (e.g. `ipd-simulator completions bash > ipd-simulator.bash`)")]
+    Completions(CompletionsArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct MatchArgs {
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), help = "Number of iterations to run for the simulation")]
+    iterations: u32,
 
-    #[arg(long, help = "Name of the first strategy (e.g., 'Random' or 'Tit for Tat')")]
-    strategy1: Option<String>,
+    #[arg(long, help = "Name of the first strategy (e.g., 'Random' or 'Tit for Tat'), or a transformer expression (e.g., 'noise(0.05, tit for tat)')")]
+    strategy1: String,
 
-    #[arg(long, help = "Name of the second strategy (e.g., 'Random' or 'Tit for Tat')")]
-    strategy2: Option<String>,
+    #[arg(long, help = "Name of the second strategy (e.g., 'Random' or 'Tit for Tat'), or a transformer expression (e.g., 'noise(0.05, tit for tat)')")]
+    strategy2: String,
 
     #[arg(long, help = "JSON file with the custom parameters for the first strategy")]
     strategy1_params: Option<String>,
@@ -27,20 +108,214 @@ struct Args {
     #[arg(long, help = "JSON file with the custom parameters for the second strategy")]
     strategy2_params: Option<String>,
 
+    #[arg(long, help = "JSON file where the first strategy's learned parameters are saved after the simulation (only for strategies that learn, e.g. 'Q-Learning')")]
+    strategy1_export_params: Option<String>,
+
+    #[arg(long, help = "JSON file where the second strategy's learned parameters are saved after the simulation (only for strategies that learn, e.g. 'Q-Learning')")]
+    strategy2_export_params: Option<String>,
+
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability, help = "Probability (between 0 and 1) that each move is flipped by mistake")]
+    noise: f64,
+
+    #[arg(long, help = "Do not tell the strategies how many iterations the simulation has (end-game aware strategies then play as if it never ended)")]
+    unknown_length: bool,
+
     #[arg(long, help = "Folder to store the simulation results per iteration in CSV format. If not provided, no CSV is created")]
-    raw_scores_folder: Option<String>,  
+    raw_scores_folder: Option<String>,
 
-    #[arg(short, long, help = "Verbose (console) simulation. Notice that this will increase the total sim time.")]
+    #[arg(short, long, help = "Verbose (console) simulation, with the reasons strategies give for their moves. Notice that this will increase the total sim time.")]
     verbose: bool,
 
-    #[arg(short, long, help = "Lists all available strategies.")]
-    list_strategies: bool,
+    #[arg(long, value_name = "FILE", help = "JSON lines file where the reasons strategies give for their moves (e.g. 'breaking deadlock') are saved, one per line (only for 'match': 'run' experiments are not traced)")]
+    trace_file: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = ProgressMode::Auto, help = "Progress display (turns completed, throughput and ETA) on stderr")]
+    progress: ProgressMode,
+
+    #[arg(long, value_name = "FILE", help = "Self-contained HTML report (configuration, scores, charts and moves) to write after the simulation")]
+    report: Option<String>,
+
+    #[arg(long, value_name = "FOLDER", help = "Folder where SVG charts of the simulation (cumulative scores and cooperation rates) are saved")]
+    plot_dir: Option<String>,
+
+    #[arg(long, value_name = "FILE", help = "Compact binary file (.ipdh) where the moves of every turn are saved, much smaller than a CSV for very long simulations (see the 'convert' command)")]
+    history_file: Option<String>,
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "FOLDER", help = "Folder where every turn and every match are exported as Parquet files (<...>_turns.parquet and <...>_matches.parquet)")]
+    parquet_dir: Option<String>,
+
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", help = "SQLite database where the run is recorded (created if needed), to be queried later with the 'query' command")]
+    db: Option<String>,
+
+    #[cfg(feature = "sqlite")]
+    #[arg(long, requires = "db", help = "Also record the moves of every turn in the database")]
+    db_moves: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+struct DescribeArgs {
+    #[arg(help = "Name (or alias) of the strategy, or a transformer expression")]
+    strategy: String,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ValidateParamsArgs {
+    #[arg(help = "Name (or alias) of the strategy, or a transformer expression")]
+    strategy: String,
+
+    #[arg(help = "JSON file with the parameters")]
+    params: String,
+}
+
+#[derive(ClapArgs, Debug)]
+struct RunArgs {
+    #[arg(required_unless_present = "resume", help = "TOML file declaring the experiment (with --resume, defaults to the one saved in the state file)")]
+    experiment: Option<String>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "resume", help = "State file where every completed match is saved, so that an interrupted run can be resumed with --resume")]
+    checkpoint: Option<String>,
+    #[arg(long, value_name = "FILE", help = "Resume the run saved in this state file (written with --checkpoint): completed matches are not played again, and new ones are saved to it")]
+    resume: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = ProgressMode::Auto, help = "Progress display (matches and turns completed, throughput and ETA) on stderr")]
+    progress: ProgressMode,
+
+    #[arg(long, value_name = "FOLDER", help = "Folder where SVG charts of every match and a heatmap of the payoffs of every matchup are saved (overrides the experiment's 'plot_dir')")]
+    plot_dir: Option<String>,
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "FOLDER", help = "Folder where every turn and every match are exported as Parquet files (<...>_turns.parquet and <...>_matches.parquet)")]
+    parquet_dir: Option<String>,
+
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", help = "SQLite database where the run is recorded (created if needed), to be queried later with the 'query' command")]
+    db: Option<String>,
+
+    #[cfg(feature = "sqlite")]
+    #[arg(long, requires = "db", help = "Also record the moves of every turn in the database")]
+    db_moves: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ProgressMode {
+    #[value(help = "A line updated in place, only when stderr is a terminal")]
+    Auto,
+    #[value(help = "No progress display")]
+    Never,
+    #[value(help = "One JSON object per line (every second, and when finished), for wrappers")]
+    Json,
+}
+
+impl ProgressMode {
+    // The progress display of `total_matches` matches of `turns` turns, if any. `verbose` runs already print
+    // every turn, so they get no progress line.
+    fn progress(self, total_matches: usize, turns: usize, verbose: bool) -> Option<Progress> {
+        match self {
+            ProgressMode::Auto if !verbose && std::io::stderr().is_terminal() => Some(Progress::new(ProgressStyle::Line, total_matches, turns)),
+            ProgressMode::Auto | ProgressMode::Never => None,
+            ProgressMode::Json => Some(Progress::new(ProgressStyle::Json, total_matches, turns)),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(ClapArgs, Debug)]
+struct QueryArgs {
+    #[arg(long, value_name = "FILE", help = "SQLite database written with --db")]
+    db: String,
+
+    #[command(subcommand)]
+    question: Question,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand, Debug)]
+enum Question {
+    #[command(about = "Latest recorded runs")]
+    Runs {
+        #[arg(long, default_value_t = 20, help = "Number of runs to show")]
+        limit: usize,
+    },
+    #[command(about = "Mean payoff per turn and cooperation rate of every player over all runs")]
+    Standings,
+    #[command(about = "Mean score of a player (label or strategy name), e.g. against an opponent and with noise")]
+    MeanScore {
+        #[arg(long, help = "Label or strategy name of the player (e.g., 'Tit for Tat')")]
+        player: String,
+
+        #[arg(long, help = "Only matches against this opponent (label or strategy name)")]
+        opponent: Option<String>,
+
+        #[arg(long, help = "Only runs with a noise strictly above this value (e.g., 0 for every noisy run)")]
+        noise_above: Option<f64>,
+
+        #[arg(long, help = "Only runs with a noise strictly below this value")]
+        noise_below: Option<f64>,
+    },
+    #[command(about = "Run any (read-only) SQL query, e.g. on the 'runs', 'players', 'matches', 'moves' tables or the 'match_scores' view")]
+    Sql {
+        #[arg(help = "SQL query")]
+        sql: String,
+    },
+}
+
+#[derive(ClapArgs, Debug)]
+struct ConvertArgs {
+    #[arg(help = "History to convert (.ipdh, .csv or .json)")]
+    input: String,
+
+    #[arg(help = "Converted history (.ipdh, .csv or .json)")]
+    output: String,
+
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "First turn to convert (starting at 1). Binary histories only read the requested turns")]
+    from_turn: Option<u64>,
+
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "Last turn to convert (included)")]
+    to_turn: Option<u64>,
 }
 
-fn determine_winner(payoff1: i32, payoff2: i32, strategy1: &dyn Strategy, strategy2: &dyn Strategy) -> String {
+#[derive(ClapArgs, Debug)]
+struct PlayArgs {
+    #[arg(long, help = "Name of the opponent strategy (e.g., 'Tit for Tat'), or a transformer expression")]
+    opponent: String,
+
+    #[arg(long, help = "JSON file with the custom parameters for the opponent strategy")]
+    opponent_params: Option<String>,
+
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), help = "Number of turns of the session. If not provided, the session lasts until you quit")]
+    iterations: Option<u32>,
+
+    #[arg(long, help = "Hide the opponent's name until the end of the session")]
+    hidden: bool,
+
+    #[arg(long, help = "Folder to store the session in CSV format (same format as the simulations). If not provided, no CSV is created")]
+    raw_scores_folder: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct CompletionsArgs {
+    #[arg(help = "Shell to generate the completions for")]
+    shell: Shell,
+}
+
+fn parse_probability(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(probability) if (0.0..=1.0).contains(&probability) => Ok(probability),
+        _ => Err("should be a number between 0 and 1".to_string()),
+    }
+}
+
+fn parse_script(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, path)) if !name.trim().is_empty() && !path.trim().is_empty() => Ok((name.trim().to_string(), path.trim().to_string())),
+        _ => Err("expected NAME=FILE".to_string()),
+    }
+}
+
+fn determine_winner(payoff1: i64, payoff2: i64, strategy1_name: &str, strategy2_name: &str) -> String {
     match payoff1.cmp(&payoff2) {
-        std::cmp::Ordering::Greater => strategy1.to_string(),
-        std::cmp::Ordering::Less    => strategy2.to_string(),
+        std::cmp::Ordering::Greater => strategy1_name.to_string(),
+        std::cmp::Ordering::Less    => strategy2_name.to_string(),
         std::cmp::Ordering::Equal   => "Tie".to_string(),
     }
 }
@@ -53,27 +328,284 @@ fn load_parameters(file_path: &str) -> Result<serde_json::Value, String> {
         .map_err(|e| format!("Failed to parse parameters JSON: {}", e))
 }
 
-fn main() -> Result<(), String> {
-    let args = Args::parse();
+fn export_parameters(file_path: &str, strategy: &dyn Strategy) -> Result<(), String> {
+    let params = strategy.export_parameters()
+        .ok_or(format!("Strategy '{}' does not support exporting its parameters", strategy))?;
+    let contents = serde_json::to_string_pretty(&params)
+        .map_err(|e| format!("Failed to serialize parameters: {}", e))?;
+    std::fs::write(file_path, contents)
+        .map_err(|e| format!("Failed to write parameters file '{}': {}", file_path, e))
+}
+
+fn play(args: PlayArgs) -> Result<(), String> {
+    let params = match &args.opponent_params {
+        Some(params_file) => Some(load_parameters(params_file)?),
+        None => None,
+    };
+    let create_opponent = || create_strategy_by_name(&args.opponent, params.clone());
+    let config = SessionConfig {
+        turns: args.iterations.map(|iterations| iterations as usize),
+        hidden_opponent: args.hidden,
+    };
+
+    let stdin = std::io::stdin();
+    let (result, opponent_name) = play_session(&create_opponent, &config, &mut stdin.lock(), &mut std::io::stdout())?;
+
+    println!("\nTurns played: {}", result.turns());
+    println!("Your cumulative score: {}", result.total1);
+    println!("{} cumulative score: {}", opponent_name, result.total2);
 
-    if args.list_strategies {
-        println!("Available strategies: - Name: Description (Aliases)");
-        println!("--------------------------------------------------");
-        for strategy_info in inventory::iter::<iterated_prisoners_dilemma_lib::strategies::StrategyInfo> {
-            println!("- {}", strategy_info);
-            println!("");
+    if let Some(folder) = &args.raw_scores_folder {
+        let human_name = "Human";
+        let (file, path) = create_csv_file(folder, human_name, &opponent_name)?;
+        let mut writer = BufWriter::new(file);
+        for turn in turns(&result, &PayoffMatrix::default()) {
+            write_csv_line(&mut writer, &turn, human_name, &opponent_name)
+                .map_err(|e| format!("Failed to write to CSV: {}", e))?;
         }
-        return Ok(());
+        writer.flush().map_err(|e| format!("Failed to write to CSV: {}", e))?;
+        println!("Session saved to {}", path.display());
     }
+    Ok(())
+}
 
-    if args.iterations == Some(0) {
-        return Err("Iterations should be > 0".to_string());
+fn register_scripts(scripts: &[(String, String)], max_operations: u64) -> Result<(), String> {
+    for (name, path) in scripts {
+        register_script(name, path, max_operations)?;
     }
+    Ok(())
+}
 
+fn run(args: RunArgs) -> Result<(), String> {
+    let mut experiment = match (&args.experiment, &args.resume) {
+        (Some(file), _) => Experiment::load(file)?,
+        (None, Some(state_file)) => Checkpoint::read_experiment(state_file)?,
+        (None, None) => unreachable!("clap requires an experiment or --resume"),
+    };
+    if args.plot_dir.is_some() {
+        experiment.output.plot_dir = args.plot_dir;
+    }
+    #[cfg(feature = "parquet")]
+    if args.parquet_dir.is_some() {
+        experiment.output.parquet_dir = args.parquet_dir;
+    }
+    // Fail before running if the database cannot be opened
+    #[cfg(feature = "sqlite")]
+    let mut store = args.db.as_deref().map(ResultStore::open).transpose()?;
+    let state_file = args.checkpoint.as_deref().or(args.resume.as_deref());
+    let checkpoint = match (&args.checkpoint, &args.resume) {
+        (Some(path), _) => Some(Checkpoint::create(path, &experiment)?),
+        (None, Some(path)) => {
+            let checkpoint = Checkpoint::resume(path, &experiment)?;
+            println!("Resuming {}: {} matches already completed", path, checkpoint.completed());
+            Some(checkpoint)
+        }
+        (None, None) => None,
+    };
+
+    // The first Ctrl-C stops the run after flushing the completed matches, a second one exits at once
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED as i32);
+        }
+        eprintln!("\nInterrupted: saving the completed matches (press Ctrl-C again to exit at once)");
+    }).map_err(|e| format!("Failed to set the Ctrl-C handler: {}", e))?;
+    let total_matches = experiment.repetitions * experiment.matchups().len();
+    let mut progress = args.progress.progress(total_matches, experiment.turns, false);
+    let control = RunControl { checkpoint, interrupted: Some(&INTERRUPTED), progress: progress.as_mut() };
     let start = Instant::now();
 
-    let strategy1_name = args.strategy1.as_deref().ok_or("--strategy1 name is required")?;
-    let strategy2_name = args.strategy2.as_deref().ok_or("--strategy2 name is required")?;
+    let mut scores = vec![(0i64, 0usize); experiment.players.len()]; // (total score, turns played)
+    let summaries = experiment.run_with(control, |summary| {
+        let (label1, label2) = (&experiment.players[summary.player1].label, &experiment.players[summary.player2].label);
+        println!(
+            "Repetition {} - {} vs {}: {} - {} ; Winner: {}",
+            summary.repetition,
+            label1,
+            label2,
+            summary.result.total1,
+            summary.result.total2,
+            determine_winner(summary.result.total1, summary.result.total2, label1, label2)
+        );
+        for (player, total) in [(summary.player1, summary.result.total1), (summary.player2, summary.result.total2)] {
+            scores[player].0 += total;
+            scores[player].1 += summary.result.turns();
+        }
+    });
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    let summaries = summaries.map_err(|e| match state_file {
+        Some(path) if INTERRUPTED.load(Ordering::SeqCst) => format!("{} (resume with --resume {})", e, path),
+        _ => e,
+    })?;
+
+    println!("\nExperiment: {}", experiment.name);
+    println!("Simulation time: {:.2?}", start.elapsed());
+    let mut standings: Vec<usize> = (0..experiment.players.len()).collect();
+    standings.sort_by_key(|&player| std::cmp::Reverse(scores[player].0));
+    for (position, player) in standings.into_iter().enumerate() {
+        let (total, turns) = scores[player];
+        println!(
+            "{}. {}: cumulative score {} (average payoff per turn: {:.4})",
+            position + 1, experiment.players[player].label, total, total as f64 / turns.max(1) as f64
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let (Some(store), Some(db)) = (store.as_mut(), &args.db) {
+        let run = RunRecord {
+            command: "run",
+            name: Some(&experiment.name),
+            config: &experiment.match_config(),
+            seed: experiment.seed,
+            repetitions: experiment.repetitions,
+            configuration: Some(&experiment.to_toml()),
+            players: &experiment.players,
+        };
+        let run_id = store.record_run(&run, &summaries, args.db_moves)?;
+        println!("Run {} recorded in {}", run_id, db);
+    }
+    Ok(())
+}
+
+fn list() {
+    println!("Available strategies: - Name: Description (Aliases)");
+    println!("--------------------------------------------------");
+    for strategy_info in inventory::iter::<StrategyInfo> {
+        println!("- {}", strategy_info);
+        println!();
+    }
+    for strategy_info in runtime_strategies() {
+        println!("- {}", strategy_info);
+        println!();
+    }
+    println!("Available transformers (wrap any strategy, and can be nested): - Name: Description (Example)");
+    println!("--------------------------------------------------");
+    for transformer_info in TRANSFORMERS {
+        println!("- {}", transformer_info);
+        println!();
+    }
+}
+
+fn describe(args: DescribeArgs) -> Result<(), String> {
+    if let Some(info) = find_runtime_strategy(&args.strategy) {
+        println!("Name: {}", info.name);
+        println!("Description: {}", info.description);
+        println!("Accepts parameters: {}", if info.supports_parameters { "Yes" } else { "No" });
+    } else if let Some(info) = find_strategy_info(&args.strategy) {
+        println!("Name: {}", info.name);
+        println!("Aliases: {}", info.aliases.join(", "));
+        println!("Description: {}", info.description);
+        println!("Accepts parameters: {}", if info.supports_parameters { "Yes" } else { "No" });
+    } else if is_transformer_expression(&args.strategy) {
+        println!("Transformer expression: {}", args.strategy);
+    }
+    // Also checks transformer expressions, and reports unknown strategies
+    let strategy = create_strategy_by_name(&args.strategy, None)?;
+    println!("Plays as: {}", strategy);
+    if let Some(p) = strategy.memory_one() {
+        println!(
+            "Memory-one cooperation probabilities: first {} ; after CC {} ; after CD {} ; after DC {} ; after DD {}",
+            p.first, p.cc, p.cd, p.dc, p.dd
+        );
+    }
+    if strategy.export_parameters().is_some() {
+        println!("Learned parameters can be exported (--strategy1-export-params / --strategy2-export-params)");
+    }
+    Ok(())
+}
+
+fn validate_params(args: ValidateParamsArgs) -> Result<(), String> {
+    let params = load_parameters(&args.params)?;
+    let strategy = create_strategy_by_name(&args.strategy, Some(params))?;
+    println!("'{}' are valid parameters for {}", args.params, strategy);
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn print_rows(rows: &QueryRows) {
+    let mut widths: Vec<usize> = rows.columns.iter().map(|column| column.chars().count()).collect();
+    for row in &rows.rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let print_line = |values: &[String]| {
+        let cells: Vec<String> = values.iter().zip(&widths).map(|(value, &width)| format!("{:<width$}", value, width = width)).collect();
+        println!("{}", cells.join(" | ").trim_end());
+    };
+    print_line(&rows.columns);
+    println!("{}", widths.iter().map(|&width| "-".repeat(width)).collect::<Vec<_>>().join("-+-"));
+    for row in &rows.rows {
+        print_line(row);
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn query(args: QueryArgs) -> Result<(), String> {
+    if !Path::new(&args.db).exists() {
+        return Err(format!("Database '{}' does not exist", args.db));
+    }
+    let store = ResultStore::open_read_only(&args.db)?;
+    match args.question {
+        Question::Runs { limit } => print_rows(&store.runs(limit)?),
+        Question::Standings => print_rows(&store.standings()?),
+        Question::Sql { sql } => print_rows(&store.query(&sql)?),
+        Question::MeanScore { player, opponent, noise_above, noise_below } => {
+            let filter = ScoreFilter { player, opponent, noise_above, noise_below };
+            let mut description = filter.player.clone();
+            if let Some(opponent) = &filter.opponent {
+                description.push_str(&format!(" against {}", opponent));
+            }
+            let noise: Vec<String> = [("noise >", filter.noise_above), ("noise <", filter.noise_below)].into_iter()
+                .filter_map(|(condition, value)| value.map(|value| format!("{} {}", condition, value)))
+                .collect();
+            if !noise.is_empty() {
+                description.push_str(&format!(" ({})", noise.join(", ")));
+            }
+            match store.mean_score(&filter)? {
+                Some(mean) => {
+                    println!("{}: {} matches in {} runs", description, mean.matches, mean.runs);
+                    println!("Mean score per match: {:.2} (opponent: {:.2})", mean.score, mean.opponent_score);
+                    println!("Mean payoff per turn: {:.4}", mean.payoff_per_turn);
+                    println!("Cooperation rate: {:.1}%", 100.0 * mean.cooperation_rate);
+                }
+                None => println!("{}: no matches recorded", description),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn convert(args: ConvertArgs) -> Result<(), String> {
+    let range = match (args.from_turn, args.to_turn) {
+        (None, None) => None,
+        (from, to) => {
+            let from = from.unwrap_or(1);
+            let to = match to {
+                Some(to) => to,
+                None if HistoryFormat::from_path(&args.input)? == HistoryFormat::Binary => HistoryReader::open(&args.input)?.turns(),
+                None => read_history(&args.input, None)?.history1.len() as u64,
+            };
+            if from > to {
+                return Err(format!("--from-turn ({}) is after --to-turn ({})", from, to));
+            }
+            Some(from - 1..to)
+        }
+    };
+    let turns = convert_history(&args.input, &args.output, range)?;
+    println!("{} turns converted to {}", turns, args.output);
+    Ok(())
+}
+
+fn completions(args: CompletionsArgs) {
+    clap_complete::generate(args.shell, &mut Args::command(), "ipd-simulator", &mut std::io::stdout());
+}
+
+fn simulate(args: MatchArgs) -> Result<(), String> {
+    let start = Instant::now();
 
     let strategy1_params = if let Some(params_file) = args.strategy1_params {
         Some(load_parameters(&params_file)?)
@@ -86,80 +618,141 @@ fn main() -> Result<(), String> {
         None
     };
 
-    let mut strategy1 = create_strategy_by_name(strategy1_name, strategy1_params)?;
-    let mut strategy2 = create_strategy_by_name(strategy2_name, strategy2_params)?;
+    #[cfg(feature = "sqlite")]
+    let player_params = [&strategy1_params, &strategy2_params].map(|params| params.as_ref().map(|p| p.to_string()));
+    #[cfg(feature = "sqlite")]
+    let mut store = args.db.as_deref().map(ResultStore::open).transpose()?;
+
+    let mut strategy1 = create_strategy_by_name(&args.strategy1, strategy1_params)?;
+    let mut strategy2 = create_strategy_by_name(&args.strategy2, strategy2_params)?;
+
+    // Fail before simulating if the learned parameters cannot be exported
+    for (file_path, strategy) in [(&args.strategy1_export_params, &strategy1), (&args.strategy2_export_params, &strategy2)] {
+        if file_path.is_some() && strategy.export_parameters().is_none() {
+            return Err(format!("Strategy '{}' does not support exporting its parameters", strategy));
+        }
+    }
 
-    let iterations = args.iterations.ok_or("Iterations are required (and should be > 0)")? as usize;
-    let mut history1: Vec<Move> = Vec::with_capacity(iterations);
-    let mut history2: Vec<Move> = Vec::with_capacity(iterations);
+    let iterations = args.iterations as usize;
 
-    let mut total1 = 0;
-    let mut total2 = 0;
+    let strategy1_name = strategy1.to_string();
+    let strategy2_name = strategy2.to_string();
 
     let mut csv_writer: Option<File> = match &args.raw_scores_folder {
-        Some(folder) => {
-            create_dir_all(folder)
-                .map_err(|e| format!("Failed to create directory '{}': {}", folder, e))?;
-    
-            let datetime = Local::now().format("%Y%m%d-%H%M%S").to_string();
-            let filename = format!(
-                "{}_Iterated-Prisioners-Dilemma-Simulation_({} vs {}).csv",
-                datetime,
-                strategy1.to_string(),
-                strategy2.to_string()
-            );
-            let path = Path::new(folder).join(filename);
-    
-            let mut file = File::create(&path)
-                .map_err(|e| format!("Failed to create file '{}': {}", path.display(), e))?;
-    
-            writeln!(file, "Iteration,Strategy 1 Name,Strategy 2 Name,Move 1,Move 2,Payoff 1,Payoff 2")
-                .map_err(|e| format!("Failed to write header to CSV: {}", e))?;
-    
-            Some(file) // Return the file if all steps succeeded
-        }
+        Some(folder) => Some(create_csv_file(folder, &strategy1_name, &strategy2_name)?.0),
         None => None, // No folder provided, so no file
     };
 
-    for iteration in 1..=iterations {
-        let move1 = strategy1.next_move(&history1, &history2);
-        let move2 = strategy2.next_move(&history2, &history1);
+    let config = MatchConfig::new(iterations)
+        .with_noise(args.noise)
+        .with_length_known(!args.unknown_length);
+
+    let mut history_writer = match &args.history_file {
+        Some(path) => {
+            let mut header = HistoryHeader::new(&strategy1_name, &strategy2_name, config.payoffs);
+            header.noise = Some(config.noise);
+            header.length_known = Some(config.length_known);
+            header.created = Some(datetime());
+            Some(HistoryWriter::create(path, &header)?)
+        }
+        None => None,
+    };
+    let mut trace_writer = match &args.trace_file {
+        Some(path) => Some(BufWriter::new(File::create(path).map_err(|e| format!("Failed to create trace file '{}': {}", path, e))?)),
+        None => None,
+    };
+    let mut write_error = None;
+
+    #[cfg(feature = "parquet")]
+    let mut parquet = match &args.parquet_dir {
+        Some(folder) => {
+            let prefix = format!("{}_Iterated-Prisioners-Dilemma-Simulation_({} vs {})", datetime(), strategy1_name, strategy2_name);
+            Some(ParquetExport::create(folder, &prefix)?)
+        }
+        None => None,
+    };
+    #[cfg(feature = "parquet")]
+    let info = MatchInfo { repetition: 1, seed: None, noise: config.noise, player1: &strategy1_name, player2: &strategy2_name };
 
-        // Calculate payoffs for this round
-        let (payoff1, payoff2) = calculate_payoffs(move1, move2);
-        total1 += payoff1;
-        total2 += payoff2;
+    let mut progress = args.progress.progress(1, iterations, args.verbose);
+    let tracing = args.verbose || trace_writer.is_some();
+    set_tracing(tracing);
+    let result = play_match(strategy1.as_mut(), strategy2.as_mut(), &config, |turn| {
+        let reasons = if tracing { take_reasons() } else { Vec::new() };
+        let player_name = |reason: &Reason| if reason.player == 1 { &strategy1_name } else { &strategy2_name };
 
         // Determine the winner
-        let winner = determine_winner(payoff1, payoff2, strategy1.as_ref(), &*strategy2);
+        let winner = determine_winner(turn.payoff1 as i64, turn.payoff2 as i64, &strategy1_name, &strategy2_name);
 
         if args.verbose {
             println!(
                 "{} - {:?} ; Payoffs: {:?} ; Winner: {}",
-                iteration,
-                (move1, move2),
-                (payoff1, payoff2),
+                turn.iteration,
+                (turn.move1, turn.move2),
+                (turn.payoff1, turn.payoff2),
                 winner
             );
+            for reason in &reasons {
+                println!("    {}: {}", player_name(reason), reason.describe());
+            }
+        }
+        if let Some(writer) = trace_writer.as_mut() {
+            for reason in &reasons {
+                let fields: serde_json::Map<String, serde_json::Value> = reason.fields.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+                let line = serde_json::json!({
+                    "turn": reason.turn,
+                    "player": reason.player,
+                    "strategy": player_name(reason),
+                    "message": reason.message,
+                    "fields": fields,
+                });
+                if let Err(e) = writeln!(writer, "{}", line) {
+                    write_error.get_or_insert(format!("Failed to write to the trace file: {}", e));
+                }
+            }
         }
 
         // Write to CSV if a folder was provided
         if let Some(ref mut writer) = csv_writer {
-            writeln!(
-                writer,
-                "{},{},{:?},{:?},{:?},{},{}",
-                iteration,
-                strategy1.to_string(),
-                strategy2.to_string(),
-                move1,
-                move2,
-                payoff1,
-                payoff2
-            ).unwrap();
+            write_csv_line(writer, turn, &strategy1_name, &strategy2_name).unwrap();
         }
 
-        history1.push(move1);
-        history2.push(move2);
+        if let Some(Err(e)) = history_writer.as_mut().map(|writer| writer.push(turn.move1, turn.move2)) {
+            write_error.get_or_insert(e);
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(Err(e)) = parquet.as_mut().map(|export| export.add_turn(&info, turn)) {
+            write_error.get_or_insert(e);
+        }
+        if let Some(progress) = progress.as_mut() {
+            progress.add_turn();
+        }
+    });
+    set_tracing(false);
+    if let Some(mut progress) = progress {
+        if result.is_ok() {
+            progress.add_match();
+        }
+        progress.finish();
+    }
+    let result = result?;
+    if let Some(e) = write_error {
+        return Err(e);
+    }
+    if let (Some(mut writer), Some(path)) = (trace_writer, &args.trace_file) {
+        writer.flush().map_err(|e| format!("Failed to write to the trace file: {}", e))?;
+        println!("Trace saved to {}", path);
+    }
+    if let (Some(writer), Some(path)) = (history_writer, &args.history_file) {
+        writer.finish()?;
+        println!("History saved to {}", path);
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(mut export) = parquet {
+        export.add_match(&info, &result)?;
+        for path in export.finish()? {
+            println!("Parquet file saved to {}", path);
+        }
     }
 
     let duration = start.elapsed();
@@ -167,8 +760,105 @@ fn main() -> Result<(), String> {
         println!("Iterations: {}", iterations);
         println!("Strategy 1: {}", strategy1.to_string());
         println!("Strategy 2: {}", strategy2.to_string());
-        println!("Strategy 1 cumulative score: {}", total1);
-        println!("Strategy 2 cumulative score: {}", total2);
+        println!("Strategy 1 cumulative score: {}", result.total1);
+        println!("Strategy 2 cumulative score: {}", result.total2);
+
+    // Memory-one matchups can be solved exactly, show how far the simulation is from the long run payoffs
+    let exact = exact_payoffs_for(strategy1.as_ref(), strategy2.as_ref(), args.noise, &config.payoffs);
+    if let Some(exact) = &exact {
+        let (average1, average2) = result.average_payoffs();
+        println!("Strategy 1 average payoff per turn: {:.4} (exact long run: {:.4})", average1, exact.payoff1);
+        println!("Strategy 2 average payoff per turn: {:.4} (exact long run: {:.4})", average2, exact.payoff2);
+    }
+
+    if let Some(report_path) = &args.report {
+        let info = ReportInfo {
+            strategy1: &strategy1_name,
+            strategy2: &strategy2_name,
+            config: &config,
+            exact: exact.as_ref(),
+            datetime: &datetime(),
+        };
+        write_html_report(report_path, &info, &result)?;
+        println!("Report saved to {}", report_path);
+    }
+
+    if let Some(folder) = &args.plot_dir {
+        let prefix = format!("{}_Iterated-Prisioners-Dilemma-Simulation_({} vs {})", datetime(), strategy1_name, strategy2_name);
+        for path in write_match_plots(folder, &prefix, &strategy1_name, &strategy2_name, &result, &config.payoffs)? {
+            println!("Plot saved to {}", path);
+        }
+    }
+
+    if let Some(file_path) = &args.strategy1_export_params {
+        export_parameters(file_path, strategy1.as_ref())?;
+    }
+    if let Some(file_path) = &args.strategy2_export_params {
+        export_parameters(file_path, strategy2.as_ref())?;
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let (Some(store), Some(db)) = (store.as_mut(), &args.db) {
+        let [params1, params2] = player_params;
+        let players = [
+            Player { label: strategy1_name, strategy: args.strategy1, params: params1 },
+            Player { label: strategy2_name, strategy: args.strategy2, params: params2 },
+        ];
+        let run = RunRecord {
+            command: "match",
+            name: None,
+            config: &config,
+            seed: None,
+            repetitions: 1,
+            configuration: None,
+            players: &players,
+        };
+        let run_id = store.record_run(&run, &[MatchSummary { repetition: 1, player1: 0, player2: 1, result }], args.db_moves)?;
+        println!("Run {} recorded in {}", run_id, db);
+    }
 
     Ok(())
 }
+
+fn execute(args: Args) -> Result<(), String> {
+    register_scripts(&args.script, args.script_max_operations)?;
+    if let Some(dir) = &args.plugin_dir {
+        register_plugins_in_dir(Path::new(dir), args.plugin_fuel, args.plugin_max_memory)?;
+    }
+
+    match args.command {
+        Command::Match(match_args) => simulate(match_args),
+        Command::List => {
+            list();
+            Ok(())
+        }
+        Command::Describe(describe_args) => describe(describe_args),
+        Command::ValidateParams(validate_args) => validate_params(validate_args),
+        Command::Play(play_args) => play(play_args),
+        Command::Run(run_args) => run(run_args),
+        #[cfg(feature = "sqlite")]
+        Command::Query(query_args) => query(query_args),
+        Command::Convert(convert_args) => convert(convert_args),
+        Command::Completions(completions_args) => {
+            completions(completions_args);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    // Invalid command lines exit here (with clap's usage error code)
+    let args = Args::parse();
+
+    match execute(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                ExitCode::from(EXIT_INTERRUPTED)
+            } else {
+                ExitCode::from(EXIT_RUNTIME_ERROR)
+            }
+        }
+    }
+}
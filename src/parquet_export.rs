@@ -0,0 +1,271 @@
+// Parquet export (`parquet` feature) of per-turn and per-match results, with typed columns, for analysis
+// tools such as Polars or DuckDB. Two files are written:
+//
+//     <prefix>_turns.parquet    one row per turn: match, repetition, seed, noise, players, turn, moves (as
+//                               `cooperate1`/`cooperate2` booleans), payoffs and whether noise flipped each move
+//     <prefix>_matches.parquet  one row per match: match, repetition, seed, noise, players, turns, scores,
+//                               cooperations and moves flipped by noise
+//
+// Both have a `match` column (counting matches from 0) to join them. Rows are written in batches of
+// `BATCH_ROWS`, so memory stays bounded however long the run is.
+use crate::Move;
+use crate::simulation::{MatchResult, Turn};
+use arrow_array::builder::{BooleanBuilder, Float64Builder, Int32Builder, Int64Builder, StringBuilder, UInt32Builder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+// Rows buffered before being handed to the Parquet writer
+pub const BATCH_ROWS: usize = 65536;
+// Rows per row group (the Parquet writer keeps up to a row group in memory)
+const ROW_GROUP_ROWS: usize = 1 << 20;
+
+// The match a turn (or a match summary) belongs to
+pub struct MatchInfo<'a> {
+    pub repetition: usize,
+    // Seed of the random numbers, if the run was seeded
+    pub seed: Option<u64>,
+    pub noise: f64,
+    pub player1: &'a str,
+    pub player2: &'a str,
+}
+
+// Column builders of a table, in the order of its schema
+trait Columns: Default {
+    fn schema() -> Schema;
+    fn finish(&mut self) -> Vec<ArrayRef>;
+}
+
+#[derive(Default)]
+struct TurnColumns {
+    match_index: UInt64Builder,
+    repetition: UInt32Builder,
+    seed: UInt64Builder,
+    noise: Float64Builder,
+    player1: StringBuilder,
+    player2: StringBuilder,
+    turn: UInt64Builder,
+    cooperate1: BooleanBuilder,
+    cooperate2: BooleanBuilder,
+    payoff1: Int32Builder,
+    payoff2: Int32Builder,
+    flipped1: BooleanBuilder,
+    flipped2: BooleanBuilder,
+}
+
+impl Columns for TurnColumns {
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("match", DataType::UInt64, false),
+            Field::new("repetition", DataType::UInt32, false),
+            Field::new("seed", DataType::UInt64, true),
+            Field::new("noise", DataType::Float64, false),
+            Field::new("player1", DataType::Utf8, false),
+            Field::new("player2", DataType::Utf8, false),
+            Field::new("turn", DataType::UInt64, false),
+            Field::new("cooperate1", DataType::Boolean, false),
+            Field::new("cooperate2", DataType::Boolean, false),
+            Field::new("payoff1", DataType::Int32, false),
+            Field::new("payoff2", DataType::Int32, false),
+            Field::new("flipped1", DataType::Boolean, false),
+            Field::new("flipped2", DataType::Boolean, false),
+        ])
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.match_index.finish()),
+            Arc::new(self.repetition.finish()),
+            Arc::new(self.seed.finish()),
+            Arc::new(self.noise.finish()),
+            Arc::new(self.player1.finish()),
+            Arc::new(self.player2.finish()),
+            Arc::new(self.turn.finish()),
+            Arc::new(self.cooperate1.finish()),
+            Arc::new(self.cooperate2.finish()),
+            Arc::new(self.payoff1.finish()),
+            Arc::new(self.payoff2.finish()),
+            Arc::new(self.flipped1.finish()),
+            Arc::new(self.flipped2.finish()),
+        ]
+    }
+}
+
+#[derive(Default)]
+struct MatchColumns {
+    match_index: UInt64Builder,
+    repetition: UInt32Builder,
+    seed: UInt64Builder,
+    noise: Float64Builder,
+    player1: StringBuilder,
+    player2: StringBuilder,
+    turns: UInt64Builder,
+    score1: Int64Builder,
+    score2: Int64Builder,
+    cooperations1: UInt64Builder,
+    cooperations2: UInt64Builder,
+    flips1: UInt64Builder,
+    flips2: UInt64Builder,
+}
+
+impl Columns for MatchColumns {
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("match", DataType::UInt64, false),
+            Field::new("repetition", DataType::UInt32, false),
+            Field::new("seed", DataType::UInt64, true),
+            Field::new("noise", DataType::Float64, false),
+            Field::new("player1", DataType::Utf8, false),
+            Field::new("player2", DataType::Utf8, false),
+            Field::new("turns", DataType::UInt64, false),
+            Field::new("score1", DataType::Int64, false),
+            Field::new("score2", DataType::Int64, false),
+            Field::new("cooperations1", DataType::UInt64, false),
+            Field::new("cooperations2", DataType::UInt64, false),
+            Field::new("flips1", DataType::UInt64, false),
+            Field::new("flips2", DataType::UInt64, false),
+        ])
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.match_index.finish()),
+            Arc::new(self.repetition.finish()),
+            Arc::new(self.seed.finish()),
+            Arc::new(self.noise.finish()),
+            Arc::new(self.player1.finish()),
+            Arc::new(self.player2.finish()),
+            Arc::new(self.turns.finish()),
+            Arc::new(self.score1.finish()),
+            Arc::new(self.score2.finish()),
+            Arc::new(self.cooperations1.finish()),
+            Arc::new(self.cooperations2.finish()),
+            Arc::new(self.flips1.finish()),
+            Arc::new(self.flips2.finish()),
+        ]
+    }
+}
+
+// A Parquet file written `BATCH_ROWS` rows at a time
+struct BatchWriter<C: Columns> {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    columns: C,
+    buffered: usize,
+    path: String,
+}
+
+impl<C: Columns> BatchWriter<C> {
+    fn create(path: String) -> Result<Self, String> {
+        let file = File::create(&path).map_err(|e| format!("Failed to create file '{}': {}", path, e))?;
+        let schema = Arc::new(C::schema());
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+            .map_err(|e| format!("Failed to create Parquet file '{}': {}", path, e))?;
+        Ok(Self { writer, schema, columns: C::default(), buffered: 0, path })
+    }
+
+    // To be called after a row has been appended to the columns
+    fn row_added(&mut self) -> Result<(), String> {
+        self.buffered += 1;
+        if self.buffered == BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), self.columns.finish())
+            .map_err(|e| format!("Failed to write to '{}': {}", self.path, e))?;
+        self.writer.write(&batch).map_err(|e| format!("Failed to write to '{}': {}", self.path, e))?;
+        self.buffered = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<String, String> {
+        self.flush()?;
+        self.writer.close().map_err(|e| format!("Failed to write to '{}': {}", self.path, e))?;
+        Ok(self.path)
+    }
+}
+
+pub struct ParquetExport {
+    turns: BatchWriter<TurnColumns>,
+    matches: BatchWriter<MatchColumns>,
+    match_index: u64,
+    // Moves flipped by noise in the current match
+    flips: (u64, u64),
+}
+
+impl ParquetExport {
+    // Creates `<folder>/<prefix>_turns.parquet` and `<folder>/<prefix>_matches.parquet` (and the folder, if needed)
+    pub fn create(folder: &str, prefix: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(folder)
+            .map_err(|e| format!("Failed to create directory '{}': {}", folder, e))?;
+        let path = |table: &str| Path::new(folder).join(format!("{}_{}.parquet", prefix, table)).display().to_string();
+        Ok(Self {
+            turns: BatchWriter::create(path("turns"))?,
+            matches: BatchWriter::create(path("matches"))?,
+            match_index: 0,
+            flips: (0, 0),
+        })
+    }
+
+    pub fn add_turn(&mut self, info: &MatchInfo, turn: &Turn) -> Result<(), String> {
+        let columns = &mut self.turns.columns;
+        columns.match_index.append_value(self.match_index);
+        columns.repetition.append_value(info.repetition as u32);
+        columns.seed.append_option(info.seed);
+        columns.noise.append_value(info.noise);
+        columns.player1.append_value(info.player1);
+        columns.player2.append_value(info.player2);
+        columns.turn.append_value(turn.iteration as u64);
+        columns.cooperate1.append_value(turn.move1 == Move::Cooperate);
+        columns.cooperate2.append_value(turn.move2 == Move::Cooperate);
+        columns.payoff1.append_value(turn.payoff1);
+        columns.payoff2.append_value(turn.payoff2);
+        columns.flipped1.append_value(turn.flipped1);
+        columns.flipped2.append_value(turn.flipped2);
+        self.flips.0 += turn.flipped1 as u64;
+        self.flips.1 += turn.flipped2 as u64;
+        self.turns.row_added()
+    }
+
+    // Adds the summary of the match whose turns were added since the previous call
+    pub fn add_match(&mut self, info: &MatchInfo, result: &MatchResult) -> Result<(), String> {
+        let cooperations = |history: &[Move]| history.iter().filter(|&&m| m == Move::Cooperate).count() as u64;
+        let columns = &mut self.matches.columns;
+        columns.match_index.append_value(self.match_index);
+        columns.repetition.append_value(info.repetition as u32);
+        columns.seed.append_option(info.seed);
+        columns.noise.append_value(info.noise);
+        columns.player1.append_value(info.player1);
+        columns.player2.append_value(info.player2);
+        columns.turns.append_value(result.turns() as u64);
+        columns.score1.append_value(result.total1);
+        columns.score2.append_value(result.total2);
+        columns.cooperations1.append_value(cooperations(&result.history1));
+        columns.cooperations2.append_value(cooperations(&result.history2));
+        columns.flips1.append_value(self.flips.0);
+        columns.flips2.append_value(self.flips.1);
+        self.match_index += 1;
+        self.flips = (0, 0);
+        self.matches.row_added()
+    }
+
+    // Writes the remaining rows and closes both files. Returns their paths.
+    pub fn finish(self) -> Result<Vec<String>, String> {
+        Ok(vec![self.turns.finish()?, self.matches.finish()?])
+    }
+}
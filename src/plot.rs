@@ -0,0 +1,206 @@
+// SVG charts of match results: cumulative scores, rolling cooperation rates and, for experiments with
+// several matches, a heatmap of the payoffs of every matchup. They are plain text (no plotting library,
+// fonts or other system dependencies), so they can be rendered anywhere, e.g. on a headless CI machine.
+use crate::{Move, PayoffMatrix};
+use crate::simulation::MatchResult;
+use std::fmt::Write as _;
+use std::path::Path;
+
+// Charts never draw more points than this (long matches are downsampled)
+const MAX_CHART_POINTS: usize = 1000;
+
+pub const COLOR1: &str = "#1f77b4";
+pub const COLOR2: &str = "#ff7f0e";
+
+// Heatmap colours for the lowest, middle and highest payoffs (red, yellow, green)
+const LOW_COLOR: (f64, f64, f64) = (215.0, 48.0, 39.0);
+const MIDDLE_COLOR: (f64, f64, f64) = (255.0, 255.0, 191.0);
+const HIGH_COLOR: (f64, f64, f64) = (26.0, 152.0, 80.0);
+
+// Escapes text to be used inside SVG (or HTML) elements and attributes
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Score of both players after every turn
+pub fn cumulative_scores(result: &MatchResult, payoffs: &PayoffMatrix) -> (Vec<f64>, Vec<f64>) {
+    let (mut total1, mut total2) = (0.0, 0.0);
+    result.history1.iter().zip(&result.history2)
+        .map(|(&move1, &move2)| {
+            let (payoff1, payoff2) = payoffs.payoffs(move1, move2);
+            total1 += payoff1 as f64;
+            total2 += payoff2 as f64;
+            (total1, total2)
+        })
+        .unzip()
+}
+
+// Cooperation rate over the last `window` turns, at every turn
+pub fn rolling_cooperation(history: &[Move], window: usize) -> Vec<f64> {
+    let mut cooperations = 0;
+    (0..history.len())
+        .map(|i| {
+            if history[i] == Move::Cooperate {
+                cooperations += 1;
+            }
+            if i >= window && history[i - window] == Move::Cooperate {
+                cooperations -= 1;
+            }
+            cooperations as f64 / (i + 1).min(window) as f64
+        })
+        .collect()
+}
+
+// Window of the rolling cooperation rate of a match with `turns` turns
+pub fn cooperation_window(turns: usize) -> usize {
+    (turns / 50).max(10)
+}
+
+fn svg_start(svg: &mut String, width: f64, height: f64) {
+    let _ = write!(
+        svg,
+        r#"<svg viewBox="0 0 {w} {h}" width="100%" role="img" font-family="sans-serif" xmlns="http://www.w3.org/2000/svg"><rect width="{w}" height="{h}" fill="white"/>"#,
+        w = width, h = height
+    );
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 { format!("{}", value) } else { format!("{:.2}", value) }
+}
+
+// Line chart of one or more series (name, colour and values, all with the same length) over the turns,
+// with the y axis going from `y_min` to `y_max`
+pub fn line_chart(series: &[(&str, &str, &[f64])], y_min: f64, y_max: f64, y_label: &str) -> String {
+    let (width, height, margin) = (800.0, 300.0, 50.0);
+    let len = series.first().map_or(0, |(_, _, values)| values.len());
+    let y_max = if y_max > y_min { y_max } else { y_min + 1.0 };
+    let x = |i: usize| margin + (width - 2.0 * margin) * i as f64 / (len.max(2) - 1) as f64;
+    let y = |v: f64| height - margin - (height - 2.0 * margin) * (v - y_min) / (y_max - y_min);
+
+    let mut svg = String::new();
+    svg_start(&mut svg, width, height);
+    // Axes and labels
+    let _ = write!(
+        svg,
+        r##"<line x1="{m}" y1="{b}" x2="{r}" y2="{b}" stroke="#444"/><line x1="{m}" y1="{m}" x2="{m}" y2="{b}" stroke="#444"/>"##,
+        m = margin, b = height - margin, r = width - margin
+    );
+    let _ = write!(svg, r#"<text x="{}" y="{}" font-size="12" text-anchor="end">{}</text>"#, margin - 5.0, y(y_max) + 4.0, format_value(y_max));
+    let _ = write!(svg, r#"<text x="{}" y="{}" font-size="12" text-anchor="end">{}</text>"#, margin - 5.0, y(y_min) + 4.0, format_value(y_min));
+    let _ = write!(svg, r#"<text x="{}" y="{}" font-size="12" text-anchor="middle">Turn</text>"#, width / 2.0, height - 10.0);
+    let _ = write!(svg, r#"<text x="{}" y="{}" font-size="12" text-anchor="end">{}</text>"#, width - margin, height - margin + 15.0, len);
+    let _ = write!(svg, r#"<text x="15" y="{}" font-size="12" transform="rotate(-90 15 {})" text-anchor="middle">{}</text>"#, height / 2.0, height / 2.0, escape_xml(y_label));
+
+    let step = len.div_ceil(MAX_CHART_POINTS).max(1);
+    for (index, (name, color, values)) in series.iter().enumerate() {
+        let points: Vec<String> = (0..len).step_by(step).chain(std::iter::once(len.saturating_sub(1)))
+            .filter(|&i| i < len)
+            .map(|i| format!("{:.1},{:.1}", x(i), y(values[i])))
+            .collect();
+        let _ = write!(svg, r#"<polyline fill="none" stroke="{}" stroke-width="2" points="{}"/>"#, color, points.join(" "));
+        // Legend
+        let legend_y = margin - 30.0 + 15.0 * index as f64;
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="12" height="12" fill="{}"/><text x="{}" y="{}" font-size="12">{}</text>"#,
+            margin + 10.0, legend_y, color, margin + 28.0, legend_y + 10.0, escape_xml(name)
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// Cumulative score of both players over the turns
+pub fn cumulative_score_chart(strategy1: &str, strategy2: &str, result: &MatchResult, payoffs: &PayoffMatrix) -> String {
+    let (cumulative1, cumulative2) = cumulative_scores(result, payoffs);
+    // With negative payoffs the scores can go below 0 (and do not always end on their highest value)
+    let scores = || cumulative1.iter().chain(&cumulative2).copied();
+    let min_score = scores().fold(0.0, f64::min);
+    let max_score = scores().fold(0.0, f64::max);
+    line_chart(&[(strategy1, COLOR1, &cumulative1), (strategy2, COLOR2, &cumulative2)], min_score, max_score, "Score")
+}
+
+// Cooperation rate of both players over the last `cooperation_window` turns
+pub fn cooperation_chart(strategy1: &str, strategy2: &str, result: &MatchResult) -> String {
+    let window = cooperation_window(result.turns());
+    let cooperation1 = rolling_cooperation(&result.history1, window);
+    let cooperation2 = rolling_cooperation(&result.history2, window);
+    line_chart(
+        &[(strategy1, COLOR1, &cooperation1), (strategy2, COLOR2, &cooperation2)],
+        0.0, 1.0, &format!("Cooperation rate (last {} turns)", window)
+    )
+}
+
+fn heatmap_color(value: f64, min: f64, max: f64) -> String {
+    let t = if max > min { ((value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.5 };
+    let (from, to, t) = if t < 0.5 { (LOW_COLOR, MIDDLE_COLOR, 2.0 * t) } else { (MIDDLE_COLOR, HIGH_COLOR, 2.0 * t - 1.0) };
+    let lerp = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
+
+// Heatmap of the average payoff per turn of each row player against each column player (`None` for
+// pairs that did not play, e.g. a player against itself). Colours go from red (`min`) to green (`max`).
+pub fn payoff_heatmap(labels: &[String], payoffs: &[Vec<Option<f64>>], min: f64, max: f64) -> String {
+    // Text has no measured width in SVG: leave room for about 7 pixels per character
+    let longest = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0) as f64;
+    let cell = 60.0;
+    let label_width = 7.0 * longest + 20.0;
+    // Column labels are rotated 45 degrees
+    let top = 5.0 * longest + 50.0;
+    let width = (label_width + cell * labels.len() as f64 + 5.0 * longest).max(520.0);
+    let height = top + cell * labels.len() as f64 + 40.0;
+
+    let mut svg = String::new();
+    svg_start(&mut svg, width, height);
+    let _ = write!(svg, r#"<text x="5" y="20" font-size="14">Average payoff per turn of the row player against the column player</text>"#);
+    for (i, label) in labels.iter().enumerate() {
+        let label = escape_xml(label);
+        let center = cell * i as f64 + cell / 2.0;
+        let _ = write!(svg, r#"<text x="{}" y="{}" font-size="12" text-anchor="end">{}</text>"#, label_width - 8.0, top + center + 4.0, label);
+        let (x, y) = (label_width + center, top - 8.0);
+        let _ = write!(svg, r#"<text x="{x}" y="{y}" font-size="12" transform="rotate(-45 {x} {y})">{label}</text>"#);
+    }
+    for (row, values) in payoffs.iter().enumerate() {
+        for (column, value) in values.iter().enumerate() {
+            let (x, y) = (label_width + cell * column as f64, top + cell * row as f64);
+            let color = value.map_or_else(|| "#eeeeee".to_string(), |value| heatmap_color(value, min, max));
+            let _ = write!(svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="white"/>"#, x, y, cell, cell, color);
+            if let Some(value) = value {
+                let _ = write!(svg, r#"<text x="{}" y="{}" font-size="13" text-anchor="middle">{:.2}</text>"#, x + cell / 2.0, y + cell / 2.0 + 5.0, value);
+            }
+        }
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="5" y="{}" font-size="12">Red: {}, green: {}</text>"#,
+        height - 12.0, format_value(min), format_value(max)
+    );
+    svg.push_str("</svg>");
+    svg
+}
+
+// Writes `<folder>/<filename>` (creating the folder, if needed) and returns its path
+pub fn write_svg(folder: &str, filename: &str, svg: &str) -> Result<String, String> {
+    std::fs::create_dir_all(folder)
+        .map_err(|e| format!("Failed to create directory '{}': {}", folder, e))?;
+    let path = Path::new(folder).join(filename);
+    std::fs::write(&path, svg)
+        .map_err(|e| format!("Failed to write plot '{}': {}", path.display(), e))?;
+    Ok(path.display().to_string())
+}
+
+// Writes `<prefix>_cumulative-score.svg` and `<prefix>_cooperation-rate.svg` into `folder`,
+// and returns their paths
+pub fn write_match_plots(
+    folder: &str,
+    prefix: &str,
+    strategy1: &str,
+    strategy2: &str,
+    result: &MatchResult,
+    payoffs: &PayoffMatrix,
+) -> Result<Vec<String>, String> {
+    Ok(vec![
+        write_svg(folder, &format!("{}_cumulative-score.svg", prefix), &cumulative_score_chart(strategy1, strategy2, result, payoffs))?,
+        write_svg(folder, &format!("{}_cooperation-rate.svg", prefix), &cooperation_chart(strategy1, strategy2, result))?,
+    ])
+}
@@ -0,0 +1,159 @@
+// Progress of long simulations on stderr: turns (and matches) completed, throughput and ETA. The clock is
+// only read every `CHECK_TURNS` turns, and the display updated at most every `LINE_INTERVAL` (or
+// `JSON_INTERVAL`), so it costs nothing noticeable even at millions of turns per second. For wrappers, it can
+// be written as one JSON object per line instead (the last one with `"finished": true`):
+//
+//     {"elapsed":1.0,"eta":7.3,"finished":false,"matches":0,"total_matches":1,"total_turns":10000000,"turns":1200000,"turns_per_second":1200000.0}
+//
+// (`eta`, in seconds like `elapsed`, is null until a turn has been played).
+use serde_json::json;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// Turns between two reads of the clock
+const CHECK_TURNS: u64 = 1024;
+const LINE_INTERVAL: Duration = Duration::from_millis(200);
+const JSON_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStyle {
+    // A single line, redrawn in place (for terminals)
+    Line,
+    // JSON lines (for wrappers)
+    Json,
+}
+
+pub struct Progress {
+    style: ProgressStyle,
+    total_turns: u64,
+    total_matches: u64,
+    turns: u64,
+    matches: u64,
+    // Turns completed before this run started (e.g. when resuming), left out of the throughput
+    skipped_turns: u64,
+    start: Instant,
+    last_update: Instant,
+    // Whether the line is currently drawn on stderr
+    drawn: bool,
+}
+
+// 1234 -> "1234", 1234567 -> "1.23M"
+fn format_count(count: f64) -> String {
+    match count {
+        c if c >= 1e9 => format!("{:.2}G", c / 1e9),
+        c if c >= 1e6 => format!("{:.2}M", c / 1e6),
+        c if c >= 1e4 => format!("{:.1}k", c / 1e3),
+        c => format!("{:.0}", c),
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, minutes, seconds) => format!("{}:{:02}", minutes, seconds),
+        (hours, minutes, seconds) => format!("{}:{:02}:{:02}", hours, minutes, seconds),
+    }
+}
+
+impl Progress {
+    // Progress of `total_matches` matches of `turns_per_match` turns each
+    pub fn new(style: ProgressStyle, total_matches: usize, turns_per_match: usize) -> Self {
+        let now = Instant::now();
+        Self {
+            style,
+            total_turns: (total_matches * turns_per_match) as u64,
+            total_matches: total_matches as u64,
+            turns: 0,
+            matches: 0,
+            skipped_turns: 0,
+            start: now,
+            last_update: now,
+            drawn: false,
+        }
+    }
+
+    pub fn add_turn(&mut self) {
+        self.turns += 1;
+        if self.turns.is_multiple_of(CHECK_TURNS) {
+            let interval = match self.style {
+                ProgressStyle::Line => LINE_INTERVAL,
+                ProgressStyle::Json => JSON_INTERVAL,
+            };
+            if self.last_update.elapsed() >= interval {
+                self.update(false);
+            }
+        }
+    }
+
+    // To be called once all the turns of a match have been added
+    pub fn add_match(&mut self) {
+        self.matches += 1;
+        // The line is redrawn at once, as it was probably cleared to print the match
+        if self.style == ProgressStyle::Line {
+            self.update(false);
+        }
+    }
+
+    // A match completed before this run started (e.g. when resuming), with `turns` turns
+    pub fn skip_match(&mut self, turns: usize) {
+        self.turns += turns as u64;
+        self.skipped_turns += turns as u64;
+        self.matches += 1;
+    }
+
+    // Removes the line from the terminal (e.g. before printing something else), until the next update
+    pub fn clear(&mut self) {
+        if self.drawn {
+            eprint!("\r\x1b[K");
+            self.drawn = false;
+        }
+    }
+
+    // Clears the line, or writes the final JSON line
+    pub fn finish(mut self) {
+        match self.style {
+            ProgressStyle::Line => self.clear(),
+            ProgressStyle::Json => self.update(true),
+        }
+    }
+
+    fn update(&mut self, finished: bool) {
+        self.last_update = Instant::now();
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = (self.turns - self.skipped_turns) as f64 / elapsed.max(1e-9);
+        let remaining = self.total_turns.saturating_sub(self.turns) as f64;
+        let eta = (rate > 0.0).then(|| remaining / rate);
+
+        let mut stderr = std::io::stderr().lock();
+        let _ = match self.style {
+            ProgressStyle::Line => {
+                let matches = match self.total_matches {
+                    1 => String::new(),
+                    total => format!("Match {}/{} | ", (self.matches + 1).min(total), total),
+                };
+                let percentage = 100.0 * self.turns as f64 / self.total_turns.max(1) as f64;
+                self.drawn = true;
+                write!(
+                    stderr,
+                    "\r\x1b[K{}{}/{} turns ({:.1}%) | {} turns/s | ETA {}",
+                    matches, format_count(self.turns as f64), format_count(self.total_turns as f64), percentage,
+                    format_count(rate), eta.map_or_else(|| "-".to_string(), format_duration)
+                )
+            }
+            ProgressStyle::Json => {
+                let line = json!({
+                    "turns": self.turns,
+                    "total_turns": self.total_turns,
+                    "matches": self.matches,
+                    "total_matches": self.total_matches,
+                    "elapsed": elapsed,
+                    "turns_per_second": rate,
+                    "eta": eta,
+                    "finished": finished,
+                });
+                writeln!(stderr, "{}", line)
+            }
+        };
+        let _ = stderr.flush();
+    }
+}
@@ -0,0 +1,142 @@
+// Self-contained HTML reports of a match: a single file with inline CSS and SVG charts (no scripts,
+// no external resources), so that it can be opened offline and shared with anyone
+use crate::Move;
+use crate::analysis::ExactPayoffs;
+use crate::plot::{cooperation_chart, cooperation_window, cumulative_score_chart, escape_xml};
+use crate::simulation::{MatchConfig, MatchResult};
+use std::fmt::Write as _;
+
+// Turns per row of the move timeline, and at most this many turns shown
+const TIMELINE_ROW: usize = 100;
+const MAX_TIMELINE_TURNS: usize = 5000;
+
+const COOPERATE_COLOR: &str = "#2ca02c";
+const DEFECT_COLOR: &str = "#d62728";
+
+pub struct ReportInfo<'a> {
+    pub strategy1: &'a str,
+    pub strategy2: &'a str,
+    pub config: &'a MatchConfig,
+    // Exact long run payoffs, for memory-one matchups
+    pub exact: Option<&'a ExactPayoffs>,
+    // When the simulation was run
+    pub datetime: &'a str,
+}
+
+fn cooperation_rate(history: &[Move]) -> f64 {
+    history.iter().filter(|&&m| m == Move::Cooperate).count() as f64 / history.len().max(1) as f64
+}
+
+// Colour-coded strips of moves (green cooperate, red defect), `TIMELINE_ROW` turns per row and both players per row
+fn move_timeline(result: &MatchResult) -> String {
+    let shown = result.turns().min(MAX_TIMELINE_TURNS);
+    let (cell, label_width) = (8.0, 70.0);
+    let rows = shown.div_ceil(TIMELINE_ROW);
+    let row_height = 2.0 * cell + 10.0;
+    let width = label_width + cell * TIMELINE_ROW as f64;
+    let height = row_height * rows as f64;
+
+    let mut svg = String::new();
+    let _ = write!(svg, r#"<svg viewBox="0 0 {} {}" width="100%" role="img" xmlns="http://www.w3.org/2000/svg">"#, width, height.max(1.0));
+    for row in 0..rows {
+        let top = row as f64 * row_height;
+        let _ = write!(svg, r#"<text x="0" y="{}" font-size="9">Turn {}</text>"#, top + cell + 4.0, row * TIMELINE_ROW + 1);
+        for (player, history) in [&result.history1, &result.history2].into_iter().enumerate() {
+            let end = ((row + 1) * TIMELINE_ROW).min(shown);
+            for (i, &m) in history[row * TIMELINE_ROW..end].iter().enumerate() {
+                let color = if m == Move::Cooperate { COOPERATE_COLOR } else { DEFECT_COLOR };
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    label_width + cell * i as f64, top + cell * player as f64, cell - 1.0, cell - 1.0, color
+                );
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+pub fn html_report(info: &ReportInfo, result: &MatchResult) -> String {
+    let (name1, name2) = (escape_xml(info.strategy1), escape_xml(info.strategy2));
+    let config = info.config;
+    let (average1, average2) = result.average_payoffs();
+    let winner = match result.total1.cmp(&result.total2) {
+        std::cmp::Ordering::Greater => name1.clone(),
+        std::cmp::Ordering::Less => name2.clone(),
+        std::cmp::Ordering::Equal => "Tie".to_string(),
+    };
+
+    let window = cooperation_window(result.turns());
+
+    let mut html = String::new();
+    let _ = write!(html, r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{name1} vs {name2} - Iterated Prisoner's Dilemma</title>
+<style>
+body {{ font-family: sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: left; }}
+th {{ background: #f4f4f4; }}
+.legend span {{ display: inline-block; width: 12px; height: 12px; margin: 0 4px 0 12px; vertical-align: middle; }}
+</style>
+</head>
+<body>
+<h1>{name1} vs {name2}</h1>
+<p>Iterated Prisoner's Dilemma simulation run on {datetime}.</p>
+"#, datetime = escape_xml(info.datetime));
+
+    let _ = write!(html, r#"<h2>Configuration</h2>
+<table>
+<tr><th>Strategy 1</th><td>{name1}</td></tr>
+<tr><th>Strategy 2</th><td>{name2}</td></tr>
+<tr><th>Iterations</th><td>{turns}</td></tr>
+<tr><th>Noise</th><td>{noise}</td></tr>
+<tr><th>Match length known by the strategies</th><td>{known}</td></tr>
+<tr><th>Payoffs (R, T, S, P)</th><td>{r}, {t}, {s}, {p}</td></tr>
+</table>
+"#,
+        turns = config.turns,
+        noise = config.noise,
+        known = if config.length_known { "Yes" } else { "No" },
+        r = config.payoffs.reward, t = config.payoffs.temptation, s = config.payoffs.sucker, p = config.payoffs.punishment,
+    );
+
+    let exact_row = match info.exact {
+        Some(exact) => format!("<tr><th>Exact long run payoff per turn</th><td>{:.4}</td><td>{:.4}</td></tr>\n", exact.payoff1, exact.payoff2),
+        None => String::new(),
+    };
+    let _ = write!(html, r#"<h2>Scores</h2>
+<table>
+<tr><th></th><th>{name1}</th><th>{name2}</th></tr>
+<tr><th>Cumulative score</th><td>{total1}</td><td>{total2}</td></tr>
+<tr><th>Average payoff per turn</th><td>{average1:.4}</td><td>{average2:.4}</td></tr>
+{exact_row}<tr><th>Cooperation rate</th><td>{rate1:.1}%</td><td>{rate2:.1}%</td></tr>
+</table>
+<p><strong>Winner:</strong> {winner}</p>
+"#,
+        total1 = result.total1, total2 = result.total2,
+        rate1 = 100.0 * cooperation_rate(&result.history1), rate2 = 100.0 * cooperation_rate(&result.history2),
+    );
+
+    let _ = write!(html, "<h2>Cumulative score</h2>\n{}\n",
+        cumulative_score_chart(info.strategy1, info.strategy2, result, &config.payoffs));
+    let _ = write!(html, "<h2>Cooperation rate</h2>\n<p>Over the last {} turns.</p>\n{}\n", window,
+        cooperation_chart(info.strategy1, info.strategy2, result));
+
+    let _ = write!(html, r#"<h2>Moves</h2>
+<p class="legend">Top row: {name1}, bottom row: {name2}.<span style="background: {COOPERATE_COLOR}"></span>Cooperate<span style="background: {DEFECT_COLOR}"></span>Defect</p>
+"#);
+    if result.turns() > MAX_TIMELINE_TURNS {
+        let _ = writeln!(html, "<p>Only the first {} turns are shown.</p>", MAX_TIMELINE_TURNS);
+    }
+    let _ = write!(html, "{}\n</body>\n</html>\n", move_timeline(result));
+    html
+}
+
+pub fn write_html_report(path: &str, info: &ReportInfo, result: &MatchResult) -> Result<(), String> {
+    std::fs::write(path, html_report(info, result))
+        .map_err(|e| format!("Failed to write report '{}': {}", path, e))
+}
@@ -0,0 +1,98 @@
+// Raw scores CSV files: one line per turn, as written with `--raw-scores-folder`
+use crate::Move;
+use crate::simulation::Turn;
+use chrono::Local;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const CSV_HEADER: &str = "Iteration,Strategy 1 Name,Strategy 2 Name,Move 1,Move 2,Payoff 1,Payoff 2";
+
+// Creates `<folder>/<datetime>_Iterated-Prisioners-Dilemma-Simulation_(<name1> vs <name2>).csv`
+// (and the folder itself, if needed) and writes the CSV header
+pub fn create_csv_file(folder: &str, strategy1_name: &str, strategy2_name: &str) -> Result<(File, PathBuf), String> {
+    let filename = format!(
+        "{}_Iterated-Prisioners-Dilemma-Simulation_({} vs {}).csv",
+        datetime(),
+        strategy1_name,
+        strategy2_name
+    );
+    create_named_csv_file(folder, &filename, "")
+}
+
+// Date and time used to name result files
+pub fn datetime() -> String {
+    Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+// Creates `<folder>/<filename>` (and the folder itself, if needed) and writes `comments`
+// (e.g. the configuration that produced the results) as `#` lines, followed by the CSV header
+pub fn create_named_csv_file(folder: &str, filename: &str, comments: &str) -> Result<(File, PathBuf), String> {
+    create_dir_all(folder)
+        .map_err(|e| format!("Failed to create directory '{}': {}", folder, e))?;
+
+    let path = Path::new(folder).join(filename);
+    let mut file = File::create(&path)
+        .map_err(|e| format!("Failed to create file '{}': {}", path.display(), e))?;
+
+    write_comment_lines(&mut file, comments)
+        .and_then(|_| writeln!(file, "{}", CSV_HEADER))
+        .map_err(|e| format!("Failed to write header to CSV: {}", e))?;
+
+    Ok((file, path))
+}
+
+// Writes every line of `text` prefixed with `# `
+pub fn write_comment_lines<W: Write>(writer: &mut W, text: &str) -> std::io::Result<()> {
+    for line in text.lines() {
+        writeln!(writer, "# {}", line)?;
+    }
+    Ok(())
+}
+
+pub fn write_csv_line<W: Write>(writer: &mut W, turn: &Turn, strategy1_name: &str, strategy2_name: &str) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{},{},{},{:?},{:?},{},{}",
+        turn.iteration,
+        strategy1_name,
+        strategy2_name,
+        turn.move1,
+        turn.move2,
+        turn.payoff1,
+        turn.payoff2
+    )
+}
+
+pub(crate) fn parse_move(field: &str) -> Option<Move> {
+    match field.trim() {
+        "Cooperate" | "C" => Some(Move::Cooperate),
+        "Defect" | "D" => Some(Move::Defect),
+        _ => None,
+    }
+}
+
+// Reads the moves of one player ("Move 1" or "Move 2" column) from a raw scores CSV.
+// Strategy names may contain commas, so the move columns are located from the end of each line.
+pub fn read_moves_from_csv(contents: &str, column: &str) -> Result<Vec<Move>, String> {
+    let offset_from_end = match column {
+        "Move 1" => 4,
+        "Move 2" => 3,
+        _ => return Err(format!("Invalid column '{}', must be 'Move 1' or 'Move 2'", column)),
+    };
+    let mut moves = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Iteration,") {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').collect();
+        let m = fields.len()
+            .checked_sub(offset_from_end)
+            .filter(|_| fields.len() >= 7)
+            .and_then(|index| parse_move(fields[index]))
+            .ok_or(format!("Invalid raw scores CSV line {}: '{}'", number + 1, line))?;
+        moves.push(m);
+    }
+    Ok(moves)
+}
@@ -0,0 +1,130 @@
+// Runs a match (a given number of turns) between two strategies
+use crate::{MatchContext, Move, PayoffMatrix, Strategy};
+
+#[derive(Clone, Copy, Debug)]
+pub struct MatchConfig {
+    pub turns: usize,
+    // Probability of each move being flipped by mistake
+    pub noise: f64,
+    pub payoffs: PayoffMatrix,
+    // Whether the strategies are told how many turns the match has
+    pub length_known: bool,
+}
+
+impl MatchConfig {
+    pub fn new(turns: usize) -> Self {
+        Self { turns, noise: 0.0, payoffs: PayoffMatrix::default(), length_known: true }
+    }
+
+    pub fn with_noise(mut self, noise: f64) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_length_known(mut self, length_known: bool) -> Self {
+        self.length_known = length_known;
+        self
+    }
+}
+
+// Outcome of a single turn, as seen by the match observer
+pub struct Turn {
+    pub iteration: usize,
+    pub move1: Move,
+    pub move2: Move,
+    pub payoff1: i32,
+    pub payoff2: i32,
+    // Whether each move was flipped by noise (i.e. it is not the move the strategy chose)
+    pub flipped1: bool,
+    pub flipped2: bool,
+}
+
+pub struct MatchResult {
+    pub history1: Vec<Move>,
+    pub history2: Vec<Move>,
+    pub total1: i64,
+    pub total2: i64,
+}
+
+impl MatchResult {
+    pub fn turns(&self) -> usize {
+        self.history1.len()
+    }
+
+    // Average payoffs per turn of both strategies
+    pub fn average_payoffs(&self) -> (f64, f64) {
+        let turns = self.turns().max(1) as f64;
+        (self.total1 as f64 / turns, self.total2 as f64 / turns)
+    }
+}
+
+// Flips the intended move with probability `noise` (i.e. a "trembling hand")
+pub fn apply_noise(intended: Move, noise: f64) -> Move {
+    if noise > 0.0 && crate::random_bool(noise) {
+        match intended {
+            Move::Cooperate => Move::Defect,
+            Move::Defect => Move::Cooperate,
+        }
+    } else {
+        intended
+    }
+}
+
+// Plays `config.turns` turns between both strategies. Each move is flipped with probability `config.noise`.
+// `on_turn` is called after every turn (e.g. to print it or to write it to a file). When tracing is on, the
+// reasons the strategies gave for their moves can be taken there (see the `trace` module).
+// Fails if a strategy cannot decide its move (see `Strategy::try_next_move`).
+pub fn play_match<F: FnMut(&Turn)>(
+    strategy1: &mut dyn Strategy,
+    strategy2: &mut dyn Strategy,
+    config: &MatchConfig,
+    on_turn: F,
+) -> Result<MatchResult, String> {
+    play_match_while(strategy1, strategy2, config, on_turn, || true)
+}
+
+// Same as `play_match`, but `keep_playing` is checked before every turn: the match stops early (with fewer
+// turns than `config.turns`) as soon as it returns `false` (e.g. when the user presses Ctrl-C)
+pub fn play_match_while<F: FnMut(&Turn), K: FnMut() -> bool>(
+    strategy1: &mut dyn Strategy,
+    strategy2: &mut dyn Strategy,
+    config: &MatchConfig,
+    mut on_turn: F,
+    mut keep_playing: K,
+) -> Result<MatchResult, String> {
+    let mut history1: Vec<Move> = Vec::with_capacity(config.turns);
+    let mut history2: Vec<Move> = Vec::with_capacity(config.turns);
+
+    let mut total1: i64 = 0;
+    let mut total2: i64 = 0;
+
+    for iteration in 1..=config.turns {
+        if !keep_playing() {
+            break;
+        }
+        let context = MatchContext {
+            total_turns: if config.length_known { Some(config.turns) } else { None },
+            current_turn: iteration,
+            payoffs: config.payoffs,
+            noise: config.noise,
+        };
+        crate::trace::begin_decision(iteration, 1);
+        let intended1 = strategy1.try_next_move(&history1, &history2, &context)?;
+        crate::trace::begin_decision(iteration, 2);
+        let intended2 = strategy2.try_next_move(&history2, &history1, &context)?;
+        let move1 = apply_noise(intended1, config.noise);
+        let move2 = apply_noise(intended2, config.noise);
+
+        // Calculate payoffs for this round
+        let (payoff1, payoff2) = config.payoffs.payoffs(move1, move2);
+        total1 += payoff1 as i64;
+        total2 += payoff2 as i64;
+
+        on_turn(&Turn { iteration, move1, move2, payoff1, payoff2, flipped1: move1 != intended1, flipped2: move2 != intended2 });
+
+        history1.push(move1);
+        history2.push(move2);
+    }
+
+    Ok(MatchResult { history1, history2, total1, total2 })
+}
@@ -1,4 +1,4 @@
-use crate::{Move, Strategy, calculate_payoffs};
+use crate::{Move, Strategy, calculate_payoffs, trace};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -40,7 +40,8 @@ impl Strategy for AdaptiveStrategy {
         }
         let c_avg = if c_count > 0 { c_total as f64 / c_count as f64 } else { 0.0 };
         let d_avg = if d_count > 0 { d_total as f64 / d_count as f64 } else { 0.0 };
-        // println!("C avg: {} (c_total/c_count -> {}/{}), D avg: {} (d_total/d_count -> {}/{})", c_avg, c_total, c_count, d_avg, d_total, d_count); // useful for debugging
+        let best = if c_avg >= d_avg { "cooperation pays at least as much" } else { "defection pays more" };
+        trace!(best, c_avg = c_avg, c_count = c_count, d_avg = d_avg, d_count = d_count);
         if c_avg >= d_avg { Move::Cooperate } else { Move::Defect }
     }
 
@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -8,6 +8,10 @@ impl Strategy for AlwaysCooperateStrategy {
     fn next_move(&mut self, _own_history: &[Move], _opponent_history: &[Move]) -> Move {
         Move::Cooperate
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 1.0, cc: 1.0, cd: 1.0, dc: 1.0, dd: 1.0 })
+    }
 }
 
 impl fmt::Display for AlwaysCooperateStrategy {
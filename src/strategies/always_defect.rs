@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -8,6 +8,10 @@ impl Strategy for AlwaysDefectStrategy {
     fn next_move(&mut self, _own_history: &[Move], _opponent_history: &[Move]) -> Move {
         Move::Defect
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 0.0, cc: 0.0, cd: 0.0, dc: 0.0, dd: 0.0 })
+    }
 }
 
 impl fmt::Display for AlwaysDefectStrategy {
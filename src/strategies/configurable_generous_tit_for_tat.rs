@@ -1,7 +1,6 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
-use rand::Rng;
 use serde_json;
 
 
@@ -21,7 +20,7 @@ impl Strategy for ConfigurableGenerousTitForTatStrategy {
     fn next_move(&mut self, _own_history: &[Move], opponent_history: &[Move]) -> Move {
         match opponent_history.last() {
             Some(&Move::Defect) => {
-                if rand::rng().random_bool(self.cooperation_probability) {
+                if crate::random_bool(self.cooperation_probability) {
                     Move::Cooperate
                 } else {
                     Move::Defect
@@ -43,6 +42,11 @@ impl Strategy for ConfigurableGenerousTitForTatStrategy {
         }
         Err("Invalid parameters. Expected 'cooperation_probability' as a number between 0 and 1".to_string())
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        let q = self.cooperation_probability;
+        Some(MemoryOne { first: 1.0, cc: 1.0, cd: q, dc: 1.0, dd: q })
+    }
 }
 
 impl fmt::Display for ConfigurableGenerousTitForTatStrategy {
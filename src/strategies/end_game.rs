@@ -0,0 +1,83 @@
+// End-game aware strategies: they exploit knowing how many turns are left in the match
+// (see `MatchContext`). When the match length is unknown they behave like the strategy they wrap.
+use crate::{MatchContext, Move, Strategy};
+use crate::strategies::{StrategyInfo, create_strategy_by_name};
+use crate::strategies::tit_for_tat::TitForTatStrategy;
+use std::fmt;
+
+// Plays like the wrapped strategy, but defects on the last `turns` turns of the match
+pub struct DefectOnLastTurnsStrategy {
+    inner: Box<dyn Strategy>,
+    turns: usize,
+}
+
+impl DefectOnLastTurnsStrategy {
+    pub fn new(inner: Box<dyn Strategy>, turns: usize) -> Self {
+        Self { inner, turns }
+    }
+
+    fn end_game_move(&self, inner_move: Move, context: &MatchContext) -> Move {
+        match context.remaining_turns() {
+            Some(remaining) if remaining < self.turns => Move::Defect,
+            _ => inner_move,
+        }
+    }
+}
+
+impl Strategy for DefectOnLastTurnsStrategy {
+    // Without context the end of the match is unknown
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.inner.next_move(own_history, opponent_history)
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        // The wrapped strategy always plays, so that its internal state stays up to date
+        let inner_move = self.inner.next_move_with_context(own_history, opponent_history, context);
+        self.end_game_move(inner_move, context)
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        let inner_move = self.inner.try_next_move(own_history, opponent_history, context)?;
+        Ok(self.end_game_move(inner_move, context))
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "strategy": "Tit for Tat", "turns": 3, "params": { ... } }
+        // "params" (the wrapped strategy's own parameters) is optional
+        let name = params.get("strategy")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'strategy' (name of the wrapped strategy)")?;
+        let turns = params.get("turns")
+            .and_then(|v| v.as_u64())
+            .ok_or("Missing or invalid 'turns' (number of final turns to defect on)")? as usize;
+        self.inner = create_strategy_by_name(name, params.get("params").cloned())?;
+        self.turns = turns;
+        Ok(())
+    }
+}
+
+impl fmt::Display for DefectOnLastTurnsStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (defects on the last {} turns)", self.inner, self.turns)
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Defect On Last Turns",
+        aliases: &["defectonlastturns", "defect_last"],
+        description: "Wraps any other strategy ('strategy' and optionally its 'params'), but defects on the last 'turns' turns of the match. Behaves like the wrapped strategy if the match length is unknown. Defaults to Tit for Tat defecting on the last turn.",
+        constructor: || Box::new(DefectOnLastTurnsStrategy::new(Box::new(TitForTatStrategy), 1)),
+        supports_parameters: true,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "End Game Tit for Tat",
+        aliases: &["endgametitfortat", "endgame_tft"],
+        description: "Plays Tit for Tat, but defects on the last 2 turns of the match (when the match length is known).",
+        constructor: || Box::new(DefectOnLastTurnsStrategy::new(Box::new(TitForTatStrategy), 2)),
+        supports_parameters: false,
+    }
+}
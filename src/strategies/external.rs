@@ -0,0 +1,260 @@
+// Implements the External strategy: the moves are decided by another program (written in any
+// language), launched as a child process that exchanges one JSON message per line over stdin/stdout.
+//
+// Protocol (every message is a single line of JSON):
+// 1. Handshake, once the process starts:
+//    -> {"type": "hello", "protocol": 1}
+//    <- {"type": "hello", "name": "My Strategy"}            ("name" is optional)
+// 2. Every turn, with the moves played on the previous turn (null on the first turn, which also tells
+//    the program that a new match starts):
+//    -> {"type": "turn", "turn": 3, "total_turns": 100, "own_last": "D", "opponent_last": "C",
+//        "payoffs": {"reward": 3, "temptation": 5, "sucker": 0, "punishment": 1}, "noise": 0.0}
+//    <- {"move": "C"}                                        ("C" or "D")
+//    ("total_turns" is null when the match length is unknown). With the "full_history" param, the message
+//    also has both whole histories ("own_history": "CD", "opponent_history": "CC"): simpler for the program,
+//    but every match then sends O(turns²) bytes.
+// 3. When the strategy is dropped:
+//    -> {"type": "end"}
+// Anything the program writes to stderr is shown as is, so it can be used for debugging.
+use crate::{MatchContext, Move, Strategy};
+use crate::strategies::StrategyInfo;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+const PROTOCOL_VERSION: u64 = 1;
+const DEFAULT_TIMEOUT_MS: u64 = 1000;
+const DEFAULT_HANDSHAKE_TIMEOUT_MS: u64 = 5000;
+
+struct ExternalProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // Lines written by the process to its stdout (read in a separate thread, so that reads can time out)
+    lines: Receiver<std::io::Result<String>>,
+}
+
+pub struct ExternalStrategy {
+    // `Mutex` only to make the strategy `Sync`, it is never contended
+    process: Option<Mutex<ExternalProcess>>,
+    command_line: String,
+    name: Option<String>,
+    timeout: Duration,
+    // Whether both whole histories are sent every turn (and not only the last moves)
+    full_history: bool,
+}
+
+fn move_string(m: Move) -> &'static str {
+    if m == Move::Cooperate { "C" } else { "D" }
+}
+
+fn history_string(history: &[Move]) -> String {
+    history.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect()
+}
+
+impl ExternalProcess {
+    fn spawn(command: &str, args: &[String]) -> Result<Self, String> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Failed to launch '{}': {}", command, e))?;
+        let stdin = child.stdin.take().ok_or("Failed to open the process stdin")?;
+        let stdout = child.stdout.take().ok_or("Failed to open the process stdout")?;
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break; // The strategy was dropped
+                }
+            }
+        });
+        Ok(Self { child, stdin, lines })
+    }
+
+    // Describes why the process stopped answering (crashed / exited / closed its stdout)
+    fn exit_reason(&mut self) -> String {
+        // Give the process a moment to finish exiting, so that its status can be reported
+        for _ in 0..10 {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return format!("the process exited ({})", status);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        "the process closed its stdout".to_string()
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), String> {
+        let result = writeln!(self.stdin, "{}", message).and_then(|_| self.stdin.flush());
+        result.map_err(|e| format!("Failed to send a message ({}): {}", e, self.exit_reason()))
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Value, String> {
+        let line = match self.lines.recv_timeout(timeout) {
+            Ok(Ok(line)) => line,
+            Ok(Err(e)) => return Err(format!("Failed to read the answer: {}", e)),
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = self.child.kill();
+                return Err(format!("No answer within {} ms, the process was killed", timeout.as_millis()));
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(format!("No answer: {}", self.exit_reason())),
+        };
+        serde_json::from_str(&line).map_err(|e| format!("Invalid JSON answer '{}': {}", line, e))
+    }
+
+    fn exchange(&mut self, message: &Value, timeout: Duration) -> Result<Value, String> {
+        self.send(message)?;
+        self.receive(timeout)
+    }
+}
+
+impl Drop for ExternalProcess {
+    fn drop(&mut self) {
+        let _ = self.send(&json!({"type": "end"}));
+        // Let the process exit on its own, and kill it if it does not
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl ExternalStrategy {
+    pub fn new() -> Self {
+        Self {
+            process: None,
+            command_line: String::new(),
+            name: None,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            full_history: false,
+        }
+    }
+
+    fn request_move(&self, own_history: &[Move], opponent_history: &[Move], context: Option<&MatchContext>) -> Result<Move, String> {
+        let process = self.process.as_ref()
+            .ok_or("No program configured, pass the 'command' to run as params")?;
+        let mut process = process.lock().map_err(|_| "The process is unusable after a previous error")?;
+
+        let mut message = json!({
+            "type": "turn",
+            "turn": own_history.len() + 1,
+            "total_turns": context.and_then(|c| c.total_turns),
+            "own_last": own_history.last().map(|&m| move_string(m)),
+            "opponent_last": opponent_history.last().map(|&m| move_string(m)),
+        });
+        if self.full_history {
+            message["own_history"] = json!(history_string(own_history));
+            message["opponent_history"] = json!(history_string(opponent_history));
+        }
+        if let Some(context) = context {
+            message["payoffs"] = json!({
+                "reward": context.payoffs.reward,
+                "temptation": context.payoffs.temptation,
+                "sucker": context.payoffs.sucker,
+                "punishment": context.payoffs.punishment,
+            });
+            message["noise"] = json!(context.noise);
+        }
+
+        let answer = process.exchange(&message, self.timeout)?;
+        match answer.get("move").and_then(|v| v.as_str()) {
+            Some("C") | Some("c") => Ok(Move::Cooperate),
+            Some("D") | Some("d") => Ok(Move::Defect),
+            _ => Err(format!("Invalid answer {}, expected something like {{\"move\": \"C\"}}", answer)),
+        }
+    }
+
+    fn play(&self, own_history: &[Move], opponent_history: &[Move], context: Option<&MatchContext>) -> Result<Move, String> {
+        self.request_move(own_history, opponent_history, context)
+            .map_err(|e| format!("External strategy '{}' (turn {}): {}", self, own_history.len() + 1, e))
+    }
+}
+
+impl Default for ExternalStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Strategy for ExternalStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history, None).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        self.play(own_history, opponent_history, Some(context)).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        self.play(own_history, opponent_history, Some(context))
+    }
+
+    fn set_parameters(&mut self, params: Value) -> Result<(), String> {
+        // Expecting: { "command": "python3", "args": ["my_strategy.py"], "timeout_ms": 1000, "handshake_timeout_ms": 5000, "full_history": false }
+        let command = params.get("command")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'command' (program to run)")?;
+        let args: Vec<String> = match params.get("args") {
+            Some(v) => v.as_array()
+                .and_then(|args| args.iter().map(|arg| arg.as_str().map(String::from)).collect())
+                .ok_or("Invalid 'args', must be an array of strings")?,
+            None => Vec::new(),
+        };
+        let read_ms = |key: &str, default: u64| match params.get(key) {
+            Some(v) => v.as_u64().filter(|&ms| ms > 0).ok_or(format!("Invalid '{}', must be a positive number of milliseconds", key)),
+            None => Ok(default),
+        };
+        let timeout = Duration::from_millis(read_ms("timeout_ms", DEFAULT_TIMEOUT_MS)?);
+        let handshake_timeout = Duration::from_millis(read_ms("handshake_timeout_ms", DEFAULT_HANDSHAKE_TIMEOUT_MS)?);
+        let full_history = match params.get("full_history") {
+            Some(v) => v.as_bool().ok_or("Invalid 'full_history', must be true or false")?,
+            None => false,
+        };
+
+        let command_line = std::iter::once(command.to_string()).chain(args.iter().cloned()).collect::<Vec<_>>().join(" ");
+        let mut process = ExternalProcess::spawn(command, &args)?;
+        let answer = process.exchange(&json!({"type": "hello", "protocol": PROTOCOL_VERSION}), handshake_timeout)
+            .map_err(|e| format!("Handshake with '{}' failed: {}", command_line, e))?;
+        if answer.get("type").and_then(|v| v.as_str()) != Some("hello") {
+            return Err(format!("Handshake with '{}' failed: expected {{\"type\": \"hello\"}}, got {}", command_line, answer));
+        }
+
+        self.name = answer.get("name").and_then(|v| v.as_str()).map(String::from);
+        self.command_line = command_line;
+        self.timeout = timeout;
+        self.full_history = full_history;
+        self.process = Some(Mutex::new(process));
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExternalStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.name, self.process.is_some()) {
+            (Some(name), _) => write!(f, "External ({})", name),
+            (None, true) => write!(f, "External ({})", self.command_line),
+            (None, false) => write!(f, "External (not configured)"),
+        }
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "External",
+        aliases: &["external", "process"],
+        description: "Moves are decided by another program (any language) exchanging one JSON message per turn over stdin/stdout. Params: 'command', 'args', 'timeout_ms' (default 1000), 'handshake_timeout_ms' (default 5000) and 'full_history' (send both whole histories every turn, instead of the last moves). The protocol is described in src/strategies/external.rs.",
+        constructor: || Box::new(ExternalStrategy::new()),
+        supports_parameters: true,
+    }
+}
@@ -0,0 +1,103 @@
+// Implements the Gambler strategy: a probabilistic Looker Up, whose lookup table maps the recent
+// history (and optionally the opponent's first moves) to a cooperation probability.
+use crate::{Move, Strategy};
+use crate::strategies::StrategyInfo;
+use crate::strategies::lookup_key::KeyLayout;
+use std::fmt;
+
+pub struct GamblerStrategy {
+    // Cooperation probability per possible key (see `KeyLayout`)
+    lookup_table: Vec<f64>,
+    layout: KeyLayout,
+}
+
+impl GamblerStrategy {
+    pub fn new() -> Self {
+        Self {
+            lookup_table: vec![1.0],
+            layout: KeyLayout { own_length: 0, opp_length: 0, initial_length: 0 },
+        }
+    }
+}
+
+impl Default for GamblerStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_probability(value: Option<&serde_json::Value>, field: &str) -> Result<f64, String> {
+    match value.and_then(|v| v.as_f64()) {
+        Some(prob) if (0.0..=1.0).contains(&prob) => Ok(prob),
+        _ => Err(format!("Invalid '{}', must be a number between 0 and 1", field)),
+    }
+}
+
+impl Strategy for GamblerStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        let probability = match self.layout.key(own_history, opponent_history) {
+            Some(key) => self.lookup_table[key],
+            None => return Move::Cooperate,
+        };
+        if crate::random_bool(probability) {
+            Move::Cooperate
+        } else {
+            Move::Defect
+        }
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "history_length": N, "initial_length": M, "default": 0.5,
+        //              "lookup_table": [ { "own": ["C",...], "opp": ["D",...], "opp_initial": ["C",...], "probability": 0.8 }, ... ] }
+        // "initial_length" (and therefore "opp_initial") and "default" (probability for the entries missing
+        // from the table, which otherwise must be complete) are optional, and
+        // "own_history_length" / "opp_history_length" can be used instead of "history_length"
+        let layout = KeyLayout::from_params(&params)?;
+        let default_probability = match params.get("default") {
+            Some(v) => Some(parse_probability(Some(v), "default")?),
+            None => None,
+        };
+        let table = params.get("lookup_table")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'lookup_table'")?;
+        let mut lookup_table = vec![None; layout.table_size()];
+        for entry in table {
+            let key = layout.entry_key(entry)?;
+            let probability = parse_probability(entry.get("probability"), "probability")?;
+            if lookup_table[key].replace(probability).is_some() {
+                return Err(format!("Duplicated lookup table entry ({})", layout.describe_key(key)));
+            }
+        }
+
+        let missing = lookup_table.iter().filter(|probability| probability.is_none()).count();
+        if missing > 0 && default_probability.is_none() {
+            let first_missing = lookup_table.iter().position(|probability| probability.is_none()).unwrap_or_default();
+            return Err(format!(
+                "Incomplete lookup table: {} of {} entries are missing (e.g. {}). Add them or provide a 'default' probability",
+                missing, lookup_table.len(), layout.describe_key(first_missing)
+            ));
+        }
+
+        self.lookup_table = lookup_table.into_iter()
+            .map(|probability| probability.or(default_probability).unwrap_or(1.0))
+            .collect();
+        self.layout = layout;
+        Ok(())
+    }
+}
+
+impl fmt::Display for GamblerStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Gambler")
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Gambler",
+        aliases: &["gambler"],
+        description: "Like Looker Up, but the lookup table maps the last N moves of both players (and optionally the opponent's first M moves) to a cooperation probability. The table must be complete unless a 'default' probability is provided. If no lookup is provided, it defaults to Cooperate.",
+        constructor: || Box::new(GamblerStrategy::new()),
+        supports_parameters: true,
+    }
+}
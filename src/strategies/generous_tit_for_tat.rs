@@ -1,7 +1,6 @@
-use crate::{Move, Strategy, REWARD, TEMPTATION, SUCKER, PUNISHMENT};
+use crate::{MemoryOne, Move, Strategy, REWARD, TEMPTATION, SUCKER, PUNISHMENT};
 use crate::strategies::StrategyInfo;
 use std::fmt;
-use rand::Rng;
 
 
 pub struct GenerousTitForTatStrategy;
@@ -24,7 +23,7 @@ impl Strategy for GenerousTitForTatStrategy {
             None => Move::Cooperate, // First move
             Some(&Move::Cooperate) => Move::Cooperate,
             Some(&Move::Defect) => {
-                if rand::rng().random_bool(GenerousTitForTatStrategy::cooperation_probability()) {
+                if crate::random_bool(GenerousTitForTatStrategy::cooperation_probability()) {
                     Move::Cooperate
                 } else {
                     Move::Defect
@@ -32,6 +31,11 @@ impl Strategy for GenerousTitForTatStrategy {
             }
         }
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        let q = Self::cooperation_probability();
+        Some(MemoryOne { first: 1.0, cc: 1.0, cd: q, dc: 1.0, dd: q })
+    }
 }
 
 impl fmt::Display for GenerousTitForTatStrategy {
@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{Move, Strategy, trace};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -27,12 +27,14 @@ impl Strategy for GradualTitForTatStrategy {
 
         // If in apology phase, cooperate for two rounds
         if self.apology_count > 0 {
+            trace!("apologizing", cooperations_left = self.apology_count);
             self.apology_count -= 1;
             return Move::Cooperate;
         }
 
         // If in retaliation phase, defect
         if self.retaliation_count < self.retaliation {
+            trace!("retaliating", defection = self.retaliation_count + 1, retaliation = self.retaliation);
             self.retaliation_count += 1;
             // If this is the last retaliation, set up apology phase
             if self.retaliation_count == self.retaliation {
@@ -47,6 +49,7 @@ impl Strategy for GradualTitForTatStrategy {
             self.retaliation = self.retaliation + new_defections;
             self.last_opponent_defections = defections;
             self.retaliation_count = 0;
+            trace!("opponent defected, retaliating", new_defections = new_defections, retaliation = self.retaliation);
             // Start retaliation
             if self.retaliation > 0 {
                 self.retaliation_count += 1;
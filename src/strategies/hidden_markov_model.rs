@@ -0,0 +1,212 @@
+// Implements a Hidden Markov Model (HMM) strategy: the player has a hidden internal state
+// (e.g. its "mood" or belief about the opponent) that changes every turn according to a
+// transition matrix selected by the opponent's last move. Each state cooperates with its own
+// (emission) probability.
+use crate::{Move, Strategy};
+use crate::strategies::StrategyInfo;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::json;
+use std::fmt;
+
+// Tolerance used when checking that probabilities add up to 1
+const EPSILON: f64 = 1e-6;
+
+pub struct HiddenMarkovModelStrategy {
+    label: String,
+    // Row `i` holds the probabilities of moving from state `i` to every state
+    transitions_c: Vec<Vec<f64>>, // used after the opponent cooperated
+    transitions_d: Vec<Vec<f64>>, // used after the opponent defected
+    emissions: Vec<f64>,          // cooperation probability of each state
+    initial_distribution: Vec<f64>,
+    state: usize,
+    rng: StdRng,
+}
+
+fn parse_probabilities(value: &serde_json::Value, field: &str) -> Result<Vec<f64>, String> {
+    let values = value.as_array().ok_or(format!("'{}' must be an array of probabilities", field))?;
+    values.iter().map(|v| match v.as_f64() {
+        Some(prob) if (0.0..=1.0).contains(&prob) => Ok(prob),
+        _ => Err(format!("Invalid probability in '{}', must be a number between 0 and 1", field)),
+    }).collect()
+}
+
+fn check_distribution(probabilities: &[f64], field: &str) -> Result<(), String> {
+    let total: f64 = probabilities.iter().sum();
+    if (total - 1.0).abs() > EPSILON {
+        return Err(format!("'{}' must add up to 1 (got {})", field, total));
+    }
+    Ok(())
+}
+
+// Parses a `states` x `states` stochastic matrix (every row adds up to 1)
+fn parse_matrix(params: &serde_json::Value, field: &str, states: usize) -> Result<Vec<Vec<f64>>, String> {
+    let rows = params.get(field)
+        .and_then(|v| v.as_array())
+        .ok_or(format!("Missing or invalid '{}' (must be a matrix)", field))?;
+    if rows.len() != states {
+        return Err(format!("'{}' must have {} rows (one per state), got {}", field, states, rows.len()));
+    }
+    let mut matrix = Vec::with_capacity(states);
+    for (i, row) in rows.iter().enumerate() {
+        let row_field = format!("{}[{}]", field, i);
+        let row = parse_probabilities(row, &row_field)?;
+        if row.len() != states {
+            return Err(format!("'{}' must have {} columns (one per state), got {}", row_field, states, row.len()));
+        }
+        check_distribution(&row, &row_field)?;
+        matrix.push(row);
+    }
+    Ok(matrix)
+}
+
+// Picks an index according to the given (valid) probability distribution
+fn sample(rng: &mut StdRng, distribution: &[f64]) -> usize {
+    let mut draw: f64 = rng.random();
+    for (i, &probability) in distribution.iter().enumerate() {
+        if draw < probability {
+            return i;
+        }
+        draw -= probability;
+    }
+    distribution.len() - 1 // Rounding errors
+}
+
+impl HiddenMarkovModelStrategy {
+    // Defaults to a single state that always cooperates
+    pub fn new() -> Self {
+        Self {
+            label: "Hidden Markov Model".to_string(),
+            transitions_c: vec![vec![1.0]],
+            transitions_d: vec![vec![1.0]],
+            emissions: vec![1.0],
+            initial_distribution: vec![1.0],
+            state: 0,
+            rng: StdRng::seed_from_u64(crate::with_rng(|rng| rng.next_u64())),
+        }
+    }
+
+    fn preset(label: &str, params: serde_json::Value) -> Self {
+        let mut strategy = Self::new();
+        strategy.set_parameters(params).expect("HMM presets are valid");
+        strategy.label = label.to_string();
+        strategy
+    }
+
+    // Two states: "trusting" (always cooperates) and "resentful" (always defects).
+    // A defection makes it resentful most of the time, and it only slowly forgives.
+    pub fn slow_forgiver() -> Self {
+        Self::preset("HMM Slow Forgiver", json!({
+            "transitions_c": [[1.0, 0.0], [0.2, 0.8]],
+            "transitions_d": [[0.1, 0.9], [0.0, 1.0]],
+            "emissions": [1.0, 0.0],
+            "initial_distribution": [1.0, 0.0]
+        }))
+    }
+
+    // Three moods (content, wary, angry) that drift with the opponent's moves
+    pub fn moody() -> Self {
+        Self::preset("HMM Moody", json!({
+            "transitions_c": [[0.9, 0.1, 0.0], [0.5, 0.4, 0.1], [0.1, 0.5, 0.4]],
+            "transitions_d": [[0.2, 0.6, 0.2], [0.0, 0.4, 0.6], [0.0, 0.1, 0.9]],
+            "emissions": [0.95, 0.6, 0.1],
+            "initial_distribution": [0.5, 0.5, 0.0]
+        }))
+    }
+}
+
+impl Default for HiddenMarkovModelStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Strategy for HiddenMarkovModelStrategy {
+    fn next_move(&mut self, _own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.state = match opponent_history.last() {
+            None => sample(&mut self.rng, &self.initial_distribution), // First move
+            Some(Move::Cooperate) => sample(&mut self.rng, &self.transitions_c[self.state]),
+            Some(Move::Defect) => sample(&mut self.rng, &self.transitions_d[self.state]),
+        };
+        if self.rng.random_bool(self.emissions[self.state]) {
+            Move::Cooperate
+        } else {
+            Move::Defect
+        }
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting (for N states): { "emissions": [N cooperation probabilities],
+        //   "transitions_c": NxN matrix, "transitions_d": NxN matrix,
+        //   "initial_distribution": [N probabilities], "seed": 42 }
+        // "initial_distribution" defaults to starting in the first state, "seed" is optional
+        let emissions = parse_probabilities(
+            params.get("emissions").ok_or("Missing 'emissions' (cooperation probability of each state)")?,
+            "emissions",
+        )?;
+        let states = emissions.len();
+        if states == 0 {
+            return Err("'emissions' must have at least one state".to_string());
+        }
+        let transitions_c = parse_matrix(&params, "transitions_c", states)?;
+        let transitions_d = parse_matrix(&params, "transitions_d", states)?;
+        let initial_distribution = match params.get("initial_distribution") {
+            Some(v) => {
+                let distribution = parse_probabilities(v, "initial_distribution")?;
+                if distribution.len() != states {
+                    return Err(format!("'initial_distribution' must have {} probabilities (one per state)", states));
+                }
+                check_distribution(&distribution, "initial_distribution")?;
+                distribution
+            }
+            None => (0..states).map(|i| if i == 0 { 1.0 } else { 0.0 }).collect(),
+        };
+        if let Some(seed) = params.get("seed") {
+            let seed = seed.as_u64().ok_or("Invalid 'seed', must be a non-negative integer")?;
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+
+        self.transitions_c = transitions_c;
+        self.transitions_d = transitions_d;
+        self.emissions = emissions;
+        self.initial_distribution = initial_distribution;
+        self.state = 0;
+        Ok(())
+    }
+}
+
+impl fmt::Display for HiddenMarkovModelStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} states)", self.label, self.emissions.len())
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Hidden Markov Model",
+        aliases: &["hiddenmarkovmodel", "hmm"],
+        description: "Has hidden internal states whose transitions depend on the opponent's last move ('transitions_c' / 'transitions_d' matrices), each state cooperating with its own probability ('emissions'). Also accepts 'initial_distribution' and a 'seed' to make its draws reproducible.",
+        constructor: || Box::new(HiddenMarkovModelStrategy::new()),
+        supports_parameters: true,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "HMM Slow Forgiver",
+        aliases: &["hmmslowforgiver", "hmm_slow_forgiver"],
+        description: "Hidden Markov Model with a trusting and a resentful state: a defection usually makes it resentful, and it only slowly forgives.",
+        constructor: || Box::new(HiddenMarkovModelStrategy::slow_forgiver()),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "HMM Moody",
+        aliases: &["hmmmoody", "hmm_moody"],
+        description: "Hidden Markov Model with three moods (content, wary, angry) that drift with the opponent's moves.",
+        constructor: || Box::new(HiddenMarkovModelStrategy::moody()),
+        supports_parameters: false,
+    }
+}
@@ -1,6 +1,5 @@
 use crate::{Move, Strategy};
 use crate::strategies::StrategyInfo;
-use rand::Rng;
 use serde_json;
 use std::fmt;
 
@@ -23,7 +22,7 @@ impl Strategy for ImperfectTitForTatStrategy {
             return Move::Cooperate;
         }
         let last_opponent_move = opponent_history.last().unwrap();
-        if rand::rng().random_bool(self.imitate_probability) {
+        if crate::random_bool(self.imitate_probability) {
             *last_opponent_move
         } else {
             match last_opponent_move {
@@ -1,80 +1,75 @@
 use crate::{Move, Strategy};
 use crate::strategies::StrategyInfo;
-use std::collections::HashMap;
+use crate::strategies::lookup_key::KeyLayout;
 use std::fmt;
 
-#[derive(Hash, PartialEq, Eq)]
-struct HistoryKey(Vec<Move>, Vec<Move>);
-
 pub struct LookerUpStrategy {
-    lookup_table: HashMap<HistoryKey, Move>,
-    history_length: usize,
+    // One move per possible key (see `KeyLayout`)
+    lookup_table: Vec<Move>,
+    layout: KeyLayout,
 }
 
 impl LookerUpStrategy {
     pub fn new() -> Self {
         Self {
-            lookup_table: HashMap::new(),
-            history_length: 0,
+            lookup_table: vec![Move::Cooperate],
+            layout: KeyLayout { own_length: 0, opp_length: 0, initial_length: 0 },
         }
     }
 }
 
+fn parse_move(value: Option<&serde_json::Value>, field: &str) -> Result<Move, String> {
+    match value.and_then(|v| v.as_str()) {
+        Some("C") => Ok(Move::Cooperate),
+        Some("D") => Ok(Move::Defect),
+        _ => Err(format!("Invalid '{}' value, must be 'C' or 'D'", field)),
+    }
+}
+
 impl Strategy for LookerUpStrategy {
     fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
-        if own_history.len() < self.history_length || opponent_history.len() < self.history_length {
-            return Move::Cooperate;
+        match self.layout.key(own_history, opponent_history) {
+            Some(key) => self.lookup_table[key],
+            None => Move::Cooperate,
         }
-
-        let own_recent = own_history[own_history.len() - self.history_length..].to_vec();
-        let opp_recent = opponent_history[opponent_history.len() - self.history_length..].to_vec();
-
-        let key = HistoryKey(own_recent, opp_recent);
-
-        self.lookup_table
-            .get(&key)
-            .copied()
-            .unwrap_or(Move::Cooperate)
     }
 
     fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
         // Expecting: { "history_length": N, "lookup_table": [ { "own": ["C",...], "opp": ["D",...], "move": "C" }, ... ] }
-        let history_length = params.get("history_length")
-            .and_then(|v| v.as_u64())
-            .ok_or("Missing or invalid 'history_length'")? as usize;
+        // Optional: "own_history_length" and "opp_history_length" (instead of "history_length"),
+        // "initial_length" (each entry then needs an "opp_initial" array with the opponent's first moves)
+        // and "default" (move for the entries missing from the table, which otherwise must be complete)
+        let layout = KeyLayout::from_params(&params)?;
+        let default_move = match params.get("default") {
+            Some(v) => Some(parse_move(Some(v), "default")?),
+            None => None,
+        };
         let table = params.get("lookup_table")
             .and_then(|v| v.as_array())
             .ok_or("Missing or invalid 'lookup_table'")?;
-        let mut lookup_table = HashMap::new();
+
+        let mut lookup_table: Vec<Option<Move>> = vec![None; layout.table_size()];
         for entry in table {
-            let own = entry.get("own")
-                .and_then(|v| v.as_array())
-                .ok_or("Each entry must have an 'own' array")?;
-            let opp = entry.get("opp")
-                .and_then(|v| v.as_array())
-                .ok_or("Each entry must have an 'opp' array")?;
-            let move_str = entry.get("move")
-                .and_then(|v| v.as_str())
-                .ok_or("Each entry must have a 'move' string")?;
-            let own_moves = own.iter().map(|m| match m.as_str() {
-                Some("C") => Ok(Move::Cooperate),
-                Some("D") => Ok(Move::Defect),
-                _ => Err("Invalid move in 'own' array, must be 'C' or 'D'"),
-            }).collect::<Result<Vec<_>,_>>()?;
-            let opp_moves = opp.iter().map(|m| match m.as_str() {
-                Some("C") => Ok(Move::Cooperate),
-                Some("D") => Ok(Move::Defect),
-                _ => Err("Invalid move in 'opp' array, must be 'C' or 'D'"),
-            }).collect::<Result<Vec<_>,_>>()?;
-            let mv = match move_str {
-                "C" => Move::Cooperate,
-                "D" => Move::Defect,
-                _ => return Err("Invalid 'move' value, must be 'C' or 'D'".to_string()),
-            };
-            lookup_table.insert(HistoryKey(own_moves, opp_moves), mv);
+            let key = layout.entry_key(entry)?;
+            let mv = parse_move(entry.get("move"), "move")?;
+            if lookup_table[key].replace(mv).is_some() {
+                return Err(format!("Duplicated lookup table entry ({})", layout.describe_key(key)));
+            }
         }
-        self.lookup_table = lookup_table;
-        self.history_length = history_length;
+
+        let missing = lookup_table.iter().filter(|mv| mv.is_none()).count();
+        if missing > 0 && default_move.is_none() {
+            let first_missing = lookup_table.iter().position(|mv| mv.is_none()).unwrap_or_default();
+            return Err(format!(
+                "Incomplete lookup table: {} of {} entries are missing (e.g. {}). Add them or provide a 'default' move",
+                missing, lookup_table.len(), layout.describe_key(first_missing)
+            ));
+        }
+
+        self.lookup_table = lookup_table.into_iter()
+            .map(|mv| mv.or(default_move).unwrap_or(Move::Cooperate))
+            .collect();
+        self.layout = layout;
         Ok(())
     }
 }
@@ -89,7 +84,7 @@ inventory::submit! {
     StrategyInfo {
         name: "Looker Up",
         aliases: &["lookerup"],
-        description: "Makes decisions based on the last N moves of both players using a predefined lookup table. If no lookup is provided, it defaults to Cooperate.",
+        description: "Makes decisions based on the last N moves of both players (optionally a different number for each player, plus the opponent's first moves) using a predefined lookup table. The table must be complete unless a 'default' move is provided. If no lookup is provided, it defaults to Cooperate.",
         constructor: || Box::new(LookerUpStrategy::new()),
         supports_parameters: true,
     }
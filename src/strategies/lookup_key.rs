@@ -0,0 +1,113 @@
+// Compact key shared by the lookup table based strategies (Looker Up, Gambler).
+// The moves are encoded as bits (Cooperate = 0, Defect = 1), from oldest to newest:
+// first the own recent moves, then the opponent recent moves and finally the opponent initial moves.
+// The resulting number is used as an index into a table with one entry per possible history.
+use crate::Move;
+
+// Keeps the tables (2^bits entries) at a reasonable size
+pub const MAX_KEY_BITS: usize = 20;
+
+pub struct KeyLayout {
+    pub own_length: usize,
+    pub opp_length: usize,
+    pub initial_length: usize,
+}
+
+fn get_length(params: &serde_json::Value, field: &str) -> Result<Option<usize>, String> {
+    match params.get(field) {
+        Some(v) => Ok(Some(v.as_u64().ok_or(format!("Invalid '{}', must be a non-negative integer", field))? as usize)),
+        None => Ok(None),
+    }
+}
+
+impl KeyLayout {
+    // Expecting "history_length" (used for both players) and/or "own_history_length" and
+    // "opp_history_length" (which take precedence), plus an optional "initial_length"
+    pub fn from_params(params: &serde_json::Value) -> Result<Self, String> {
+        let history_length = get_length(params, "history_length")?;
+        let own_length = get_length(params, "own_history_length")?.or(history_length)
+            .ok_or("Missing or invalid 'history_length' (or 'own_history_length')")?;
+        let opp_length = get_length(params, "opp_history_length")?.or(history_length)
+            .ok_or("Missing or invalid 'history_length' (or 'opp_history_length')")?;
+        let initial_length = get_length(params, "initial_length")?.unwrap_or(0);
+
+        let layout = Self { own_length, opp_length, initial_length };
+        if layout.bits() > MAX_KEY_BITS {
+            return Err(format!(
+                "The lookup table is too big: own, opponent and initial lengths add up to {} moves (max {})",
+                layout.bits(), MAX_KEY_BITS
+            ));
+        }
+        Ok(layout)
+    }
+
+    pub fn bits(&self) -> usize {
+        self.own_length + self.opp_length + self.initial_length
+    }
+
+    // Number of entries of a complete lookup table
+    pub fn table_size(&self) -> usize {
+        1 << self.bits()
+    }
+
+    // Number of turns that have to be played before the table can be used
+    pub fn needed_turns(&self) -> usize {
+        self.own_length.max(self.opp_length).max(self.initial_length)
+    }
+
+    // Key for the current histories, `None` if there is not enough history yet
+    pub fn key(&self, own_history: &[Move], opponent_history: &[Move]) -> Option<usize> {
+        let needed = self.needed_turns();
+        if own_history.len() < needed || opponent_history.len() < needed {
+            return None;
+        }
+        let own_recent = &own_history[own_history.len() - self.own_length..];
+        let opp_recent = &opponent_history[opponent_history.len() - self.opp_length..];
+        let opp_initial = &opponent_history[..self.initial_length];
+        Some(
+            own_recent.iter()
+                .chain(opp_recent)
+                .chain(opp_initial)
+                .fold(0, |key, &m| (key << 1) | (m == Move::Defect) as usize)
+        )
+    }
+
+    // Key of a lookup table entry: { "own": ["C",...], "opp": ["D",...], "opp_initial": ["C",...] }
+    // ("opp_initial" is only needed when "initial_length" > 0)
+    pub fn entry_key(&self, entry: &serde_json::Value) -> Result<usize, String> {
+        let mut key = 0;
+        for (field, expected_length) in [("own", self.own_length), ("opp", self.opp_length), ("opp_initial", self.initial_length)] {
+            let moves = match entry.get(field) {
+                Some(value) => value.as_array().ok_or(format!("'{}' must be an array of 'C'/'D'", field))?,
+                None if expected_length == 0 => continue,
+                None => return Err(format!("Each entry must have an '{}' array", field)),
+            };
+            if moves.len() != expected_length {
+                return Err(format!("Invalid '{}' array length {}, expected {}", field, moves.len(), expected_length));
+            }
+            for m in moves {
+                let bit = match m.as_str() {
+                    Some("C") => 0,
+                    Some("D") => 1,
+                    _ => return Err(format!("Invalid move in '{}' array, must be 'C' or 'D'", field)),
+                };
+                key = (key << 1) | bit;
+            }
+        }
+        Ok(key)
+    }
+
+    // Human readable version of a key (e.g. "own: CD, opp: DD"), used in error messages
+    pub fn describe_key(&self, key: usize) -> String {
+        let moves: String = (0..self.bits()).rev()
+            .map(|bit| if (key >> bit) & 1 == 1 { 'D' } else { 'C' })
+            .collect();
+        let (own, rest) = moves.split_at(self.own_length);
+        let (opp, initial) = rest.split_at(self.opp_length);
+        if self.initial_length > 0 {
+            format!("own: {}, opp: {}, opp_initial: {}", own, opp, initial)
+        } else {
+            format!("own: {}, opp: {}", own, opp)
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// Meta-strategies: players built from a team of other registered strategies.
+// Every turn each team member decides the move it would play (all of them see the meta player's
+// actual history) and its would-be score against the opponent's actual moves is tracked.
+// The meta player then plays the majority vote, the move of the best performing member so far,
+// or the move of a random member.
+use crate::{calculate_payoffs, MatchContext, Move, Strategy};
+use crate::strategies::{StrategyInfo, create_strategy_by_name};
+use rand::Rng;
+use std::fmt;
+
+const DEFAULT_TEAM: &[&str] = &["Tit for Tat", "Always Defect", "Win-Stay, Lose-Shift", "Grim Trigger", "Generous Tit for Tat"];
+
+#[derive(Clone, Copy)]
+pub enum MetaKind {
+    Majority,
+    Winner,
+    Random,
+}
+
+pub struct MetaStrategy {
+    kind: MetaKind,
+    team: Vec<Box<dyn Strategy>>,
+    // Would-be total payoff of each team member in the current match
+    scores: Vec<i64>,
+    // Move each team member wanted to play on the last turn
+    last_moves: Vec<Move>,
+}
+
+impl MetaStrategy {
+    pub fn new(kind: MetaKind) -> Self {
+        let team = DEFAULT_TEAM.iter()
+            .map(|name| create_strategy_by_name(name, None).expect("the default team members are registered"))
+            .collect();
+        Self::with_team(kind, team)
+    }
+
+    pub fn with_team(kind: MetaKind, team: Vec<Box<dyn Strategy>>) -> Self {
+        Self { kind, scores: vec![0; team.len()], last_moves: Vec::with_capacity(team.len()), team }
+    }
+
+    // Only fails (with a context) if a team member does
+    fn play(&mut self, own_history: &[Move], opponent_history: &[Move], context: Option<&MatchContext>) -> Result<Move, String> {
+        if own_history.is_empty() {
+            self.scores.iter_mut().for_each(|score| *score = 0); // New match
+        } else if let Some(&opponent_last) = opponent_history.last() {
+            for (score, &member_move) in self.scores.iter_mut().zip(&self.last_moves) {
+                let (payoff, _) = match context {
+                    Some(context) => context.payoffs.payoffs(member_move, opponent_last),
+                    None => calculate_payoffs(member_move, opponent_last),
+                };
+                *score += payoff as i64;
+            }
+        }
+
+        self.last_moves.clear();
+        for member in self.team.iter_mut() {
+            self.last_moves.push(match context {
+                Some(context) => member.try_next_move(own_history, opponent_history, context)?,
+                None => member.next_move(own_history, opponent_history),
+            });
+        }
+
+        Ok(match self.kind {
+            MetaKind::Majority => {
+                let cooperators = self.last_moves.iter().filter(|&&m| m == Move::Cooperate).count();
+                if 2 * cooperators >= self.last_moves.len() { Move::Cooperate } else { Move::Defect }
+            }
+            MetaKind::Winner => {
+                // The first of the best scoring members (so ties favour the earlier ones)
+                let best = (0..self.scores.len()).rev().max_by_key(|&i| self.scores[i]).unwrap_or(0);
+                self.last_moves[best]
+            }
+            MetaKind::Random => self.last_moves[crate::with_rng(|rng| rng.random_range(0..self.last_moves.len()))],
+        })
+    }
+}
+
+impl Strategy for MetaStrategy {
+    // Without a way to report errors, a team member failing to decide its move (e.g. an External program
+    // that crashed) panics here. The match runner uses `try_next_move`, which returns the error instead.
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history, None).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        self.play(own_history, opponent_history, Some(context)).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        self.play(own_history, opponent_history, Some(context))
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "team": [ "Tit for Tat", "noise(0.1, always defect)", { "strategy": "Majority", "params": { ... } }, ... ] }
+        let members = params.get("team")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'team' (array of strategy names)")?;
+        if members.is_empty() {
+            return Err("'team' must have at least one strategy".to_string());
+        }
+        let mut team = Vec::with_capacity(members.len());
+        for member in members {
+            let strategy = match member {
+                serde_json::Value::String(name) => create_strategy_by_name(name, None)?,
+                serde_json::Value::Object(_) => {
+                    let name = member.get("strategy")
+                        .and_then(|v| v.as_str())
+                        .ok_or("Each team member object must have a 'strategy' name")?;
+                    create_strategy_by_name(name, member.get("params").cloned())?
+                }
+                _ => return Err("Each team member must be a strategy name or a { \"strategy\": ..., \"params\": ... } object".to_string()),
+            };
+            team.push(strategy);
+        }
+        *self = Self::with_team(self.kind, team);
+        Ok(())
+    }
+}
+
+impl fmt::Display for MetaStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            MetaKind::Majority => "Meta Majority",
+            MetaKind::Winner => "Meta Winner",
+            MetaKind::Random => "Meta Random",
+        };
+        let team: Vec<String> = self.team.iter().map(|member| member.to_string()).collect();
+        write!(f, "{} [{}]", kind, team.join(" | "))
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Meta Majority",
+        aliases: &["metamajority", "meta_majority"],
+        description: "Plays what most members of its team of strategies would play (Cooperate on ties). The 'team' (strategy names or transformer expressions) can be passed as params.",
+        constructor: || Box::new(MetaStrategy::new(MetaKind::Majority)),
+        supports_parameters: true,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Meta Winner",
+        aliases: &["metawinner", "meta_winner"],
+        description: "Plays what the member of its team with the highest would-be score so far would play. The 'team' (strategy names or transformer expressions) can be passed as params.",
+        constructor: || Box::new(MetaStrategy::new(MetaKind::Winner)),
+        supports_parameters: true,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Meta Random",
+        aliases: &["metarandom", "meta_random"],
+        description: "Plays what a randomly chosen member of its team would play (a different one every turn). The 'team' (strategy names or transformer expressions) can be passed as params.",
+        constructor: || Box::new(MetaStrategy::new(MetaKind::Random)),
+        supports_parameters: true,
+    }
+}
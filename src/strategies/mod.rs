@@ -1,6 +1,7 @@
 // src/strategies/mod.rs
 use crate::Strategy;
 use std::fmt;
+use std::sync::{Arc, RwLock};
 
 pub mod random;
 pub mod tit_for_tat;
@@ -20,6 +21,18 @@ pub mod firm_majority;
 pub mod majority;
 pub mod stochastic_tit_for_tat;
 pub mod looker_up;
+pub mod gambler;
+pub mod lookup_key;
+pub mod hidden_markov_model;
+pub mod neural_network;
+pub mod q_learning;
+pub mod end_game;
+pub mod transformers;
+pub mod meta;
+pub mod replay;
+pub mod external;
+pub mod script;
+pub mod wasm_plugin;
 pub mod forgiving;
 pub mod reverse_tit_for_tat;
 pub mod probability_cooperator;
@@ -29,6 +42,7 @@ pub mod generous_tit_for_tat;
 pub mod imperfect_tit_for_tat;
 pub mod two_tit_for_tats;
 pub mod omega_tit_for_tat;
+pub mod zero_determinant;
 
 // Alias for the strategy constructor function
 // Must return a Box<dyn Strategy>
@@ -60,39 +74,96 @@ impl fmt::Display for StrategyInfo {
 // Tell `inventory` to collect all instances of `StrategyInfo`
 inventory::collect!(StrategyInfo);
 
+// Strategies registered while running (e.g. scripts loaded from the command line, see `script`),
+// which cannot be collected by `inventory` at compile time
+pub struct RuntimeStrategyInfo {
+    pub name: String,
+    pub description: String,
+    pub constructor: Box<dyn Fn() -> Result<Box<dyn Strategy>, String> + Send + Sync>,
+    pub supports_parameters: bool,
+}
+
+impl fmt::Display for RuntimeStrategyInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (accepts parameters: {})",
+            self.name,
+            self.description,
+            if self.supports_parameters { "Yes" } else { "No" }
+        )
+    }
+}
+
+static RUNTIME_STRATEGIES: RwLock<Vec<Arc<RuntimeStrategyInfo>>> = RwLock::new(Vec::new());
+
+fn is_name_taken(lower_name: &str) -> bool {
+    inventory::iter::<StrategyInfo>().any(|si| {
+        si.name.to_lowercase() == lower_name || si.aliases.iter().any(|alias| alias.to_lowercase() == lower_name)
+    }) || runtime_strategies().iter().any(|info| info.name.to_lowercase() == lower_name)
+}
+
+// Registers a strategy under a name that is not used by any other strategy
+pub fn register_runtime_strategy(info: RuntimeStrategyInfo) -> Result<(), String> {
+    if info.name.trim().is_empty() || info.name.contains(['(', ')', ',']) {
+        return Err(format!("Invalid strategy name '{}', it must not be empty nor contain parentheses or commas", info.name));
+    }
+    if is_name_taken(&info.name.to_lowercase()) {
+        return Err(format!("There is already a strategy called '{}'", info.name));
+    }
+    RUNTIME_STRATEGIES.write().expect("the runtime strategies lock is never poisoned").push(Arc::new(info));
+    Ok(())
+}
+
+pub fn runtime_strategies() -> Vec<Arc<RuntimeStrategyInfo>> {
+    RUNTIME_STRATEGIES.read().expect("the runtime strategies lock is never poisoned").clone()
+}
+
+pub fn find_runtime_strategy(name: &str) -> Option<Arc<RuntimeStrategyInfo>> {
+    let lower_name = name.to_lowercase();
+    runtime_strategies().into_iter().find(|info| info.name.to_lowercase() == lower_name)
+}
+
+// Finds a built-in strategy by its name or one of its aliases (case-insensitive)
+pub fn find_strategy_info(name: &str) -> Option<&'static StrategyInfo> {
+    let lower_name = name.to_lowercase();
+    inventory::iter::<StrategyInfo>().find(|strategy_info| {
+        strategy_info.name.to_lowercase() == lower_name
+            || strategy_info.aliases.iter().any(|alias| alias.to_lowercase() == lower_name)
+    })
+}
+
 // Helper function to create strategies by name
+// (or by transformer expression, e.g. `noise(0.05, tit for tat)`, see `transformers`)
 pub fn create_strategy_by_name(name: &str, params: Option<serde_json::Value>) -> Result<Box<dyn Strategy>, String> {
-    let lower_name = name.to_lowercase();
-    for strategy_info in inventory::iter::<StrategyInfo> {
-        // Check the main name (case-insensitive)
-        if strategy_info.name.to_lowercase() == lower_name {
-            let mut strategy = (strategy_info.constructor)();
-            if let Some(params) = params {
-                if !strategy_info.supports_parameters {
-                    return Err(format!("Strategy '{}' does not support parameters", name));
-                }
-                strategy.set_parameters(params)?;
+    if transformers::is_transformer_expression(name) {
+        return transformers::create_transformed_strategy(name, params);
+    }
+    if let Some(info) = find_runtime_strategy(name) {
+        let mut strategy = (info.constructor)()?;
+        if let Some(params) = params {
+            if !info.supports_parameters {
+                return Err(format!("Strategy '{}' does not support parameters", name));
             }
-            return Ok(strategy);
+            strategy.set_parameters(params)?;
         }
-        // Check aliases (case-insensitive)
-        for alias in strategy_info.aliases {
-            if alias.to_lowercase() == lower_name {
-                let mut strategy = (strategy_info.constructor)();
-                if let Some(params) = params {
-                    if !strategy_info.supports_parameters {
-                        return Err(format!("Strategy '{}' does not support parameters", name));
-                    }
-                    strategy.set_parameters(params)?;
-                }
-                return Ok(strategy);
+        return Ok(strategy);
+    }
+    if let Some(strategy_info) = find_strategy_info(name) {
+        let mut strategy = (strategy_info.constructor)();
+        if let Some(params) = params {
+            if !strategy_info.supports_parameters {
+                return Err(format!("Strategy '{}' does not support parameters", name));
             }
+            strategy.set_parameters(params)?;
         }
+        return Ok(strategy);
     }
 
     // If not found, provide a helpful error message
     let available_strategies: Vec<String> = inventory::iter::<StrategyInfo>()
         .map(|si| si.name.to_string())
+        .chain(runtime_strategies().iter().map(|info| info.name.clone()))
         .collect();
     Err(format!(
         "Unknown strategy: '{}'. Available strategies are: {}",
@@ -0,0 +1,247 @@
+// Implements a strategy driven by a small feed-forward neural network whose architecture and
+// weights are loaded via parameters (e.g. trained elsewhere). It cooperates when the network's
+// output is above a threshold.
+//
+// Input features (moves are encoded as 1.0 = Cooperate, -1.0 = Defect, 0.0 = not played yet):
+//   - the last `history_length` own moves (oldest first)
+//   - the last `history_length` opponent moves (oldest first)
+//   - own and opponent cooperation rates so far (0.0 before the first move)
+//   - the turn number (number of moves already played)
+//   - the opponent's first `first_moves` moves
+use crate::{Move, Strategy};
+use crate::strategies::StrategyInfo;
+use std::fmt;
+
+struct Layer {
+    weights: Vec<Vec<f64>>, // one row (of `inputs` weights) per output neuron
+    biases: Vec<f64>,
+}
+
+pub struct NeuralNetworkStrategy {
+    history_length: usize,
+    first_moves: usize,
+    layers: Vec<Layer>,
+    threshold: f64,
+    // Reused buffers, to avoid allocating on every move
+    features: Vec<f64>,
+    activations: Vec<f64>,
+    // Running counts for the cooperation rates, so that they don't rescan the histories
+    own_cooperation: CooperationCount,
+    opponent_cooperation: CooperationCount,
+}
+
+// Cooperations among the first `counted` moves of a history
+#[derive(Default)]
+struct CooperationCount {
+    counted: usize,
+    cooperations: usize,
+}
+
+fn encode(m: Option<&Move>) -> f64 {
+    match m {
+        Some(Move::Cooperate) => 1.0,
+        Some(Move::Defect) => -1.0,
+        None => 0.0,
+    }
+}
+
+impl CooperationCount {
+    // Counts the moves added to `history` since the last call. Histories only grow during a match: a shorter
+    // one belongs to another match (or a rebuilt one), and is counted again from the start.
+    fn rate(&mut self, history: &[Move]) -> f64 {
+        if history.len() < self.counted {
+            *self = Self::default();
+        }
+        self.cooperations += history[self.counted..].iter().filter(|&&m| m == Move::Cooperate).count();
+        self.counted = history.len();
+        if self.counted == 0 {
+            return 0.0;
+        }
+        self.cooperations as f64 / self.counted as f64
+    }
+}
+
+fn parse_numbers(value: &serde_json::Value, field: &str) -> Result<Vec<f64>, String> {
+    value.as_array()
+        .ok_or(format!("'{}' must be an array of numbers", field))?
+        .iter()
+        .map(|v| v.as_f64().ok_or(format!("Invalid number in '{}'", field)))
+        .collect()
+}
+
+impl NeuralNetworkStrategy {
+    // Defaults to a network without layers (whose output is always 0.0) and a threshold below it: always cooperates
+    pub fn new() -> Self {
+        Self {
+            history_length: 0,
+            first_moves: 0,
+            layers: Vec::new(),
+            threshold: -1.0,
+            features: Vec::new(),
+            activations: Vec::new(),
+            own_cooperation: CooperationCount::default(),
+            opponent_cooperation: CooperationCount::default(),
+        }
+    }
+
+    // Called before the first move of every match
+    fn reset(&mut self) {
+        self.own_cooperation = CooperationCount::default();
+        self.opponent_cooperation = CooperationCount::default();
+    }
+
+    // Number of input features for the given configuration
+    pub fn input_size(history_length: usize, first_moves: usize) -> usize {
+        2 * history_length + 3 + first_moves
+    }
+
+    fn compute_features(&mut self, own_history: &[Move], opponent_history: &[Move]) {
+        let turn = own_history.len();
+        self.features.clear();
+        for history in [own_history, opponent_history] {
+            for i in (1..=self.history_length).rev() {
+                self.features.push(encode(turn.checked_sub(i).and_then(|idx| history.get(idx))));
+            }
+        }
+        let own_rate = self.own_cooperation.rate(own_history);
+        let opponent_rate = self.opponent_cooperation.rate(opponent_history);
+        self.features.push(own_rate);
+        self.features.push(opponent_rate);
+        self.features.push(turn as f64);
+        for i in 0..self.first_moves {
+            self.features.push(encode(opponent_history.get(i)));
+        }
+    }
+
+    // Runs the network on `self.features`: ReLU on the hidden layers, linear output
+    fn output(&mut self) -> f64 {
+        if self.layers.is_empty() {
+            return 0.0;
+        }
+        let last = self.layers.len() - 1;
+        for (index, layer) in self.layers.iter().enumerate() {
+            self.activations.clear();
+            for (row, bias) in layer.weights.iter().zip(&layer.biases) {
+                let sum = row.iter().zip(&self.features).map(|(w, x)| w * x).sum::<f64>() + bias;
+                self.activations.push(if index < last { sum.max(0.0) } else { sum });
+            }
+            std::mem::swap(&mut self.features, &mut self.activations);
+        }
+        self.features[0]
+    }
+}
+
+impl Default for NeuralNetworkStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Strategy for NeuralNetworkStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        if own_history.is_empty() {
+            self.reset();
+        }
+        self.compute_features(own_history, opponent_history);
+        if self.output() > self.threshold {
+            Move::Cooperate
+        } else {
+            Move::Defect
+        }
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "history_length": K, "first_moves": M, "threshold": 0.0,
+        //   "layers": [input size, hidden sizes..., 1],
+        //   "weights": [one matrix (output size x input size) per layer], "biases": [one vector per layer] }
+        // "first_moves" (default 0) and "threshold" (default 0.0) are optional
+        let history_length = params.get("history_length")
+            .and_then(|v| v.as_u64())
+            .ok_or("Missing or invalid 'history_length'")? as usize;
+        let first_moves = match params.get("first_moves") {
+            Some(v) => v.as_u64().ok_or("Invalid 'first_moves', must be a non-negative integer")? as usize,
+            None => 0,
+        };
+        let threshold = match params.get("threshold") {
+            Some(v) => v.as_f64().ok_or("Invalid 'threshold', must be a number")?,
+            None => 0.0,
+        };
+
+        let sizes = params.get("layers")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'layers' (e.g. [7, 4, 1])")?
+            .iter()
+            .map(|v| v.as_u64().filter(|&size| size > 0).map(|size| size as usize))
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Invalid 'layers', sizes must be positive integers")?;
+        if sizes.len() < 2 {
+            return Err("'layers' must have at least the input and the output sizes".to_string());
+        }
+        let input_size = Self::input_size(history_length, first_moves);
+        if sizes[0] != input_size {
+            return Err(format!(
+                "The input layer must have {} neurons (2 * history_length + 3 + first_moves), got {}",
+                input_size, sizes[0]
+            ));
+        }
+        if sizes[sizes.len() - 1] != 1 {
+            return Err("The output layer must have a single neuron".to_string());
+        }
+
+        let weights = params.get("weights")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'weights'")?;
+        let biases = params.get("biases")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'biases'")?;
+        if weights.len() != sizes.len() - 1 || biases.len() != sizes.len() - 1 {
+            return Err(format!(
+                "'weights' and 'biases' must have one entry per layer ({}), got {} and {}",
+                sizes.len() - 1, weights.len(), biases.len()
+            ));
+        }
+
+        let mut layers = Vec::with_capacity(sizes.len() - 1);
+        for (i, (layer_weights, layer_biases)) in weights.iter().zip(biases).enumerate() {
+            let (inputs, outputs) = (sizes[i], sizes[i + 1]);
+            let rows = layer_weights.as_array().ok_or(format!("'weights[{}]' must be a matrix", i))?;
+            if rows.len() != outputs {
+                return Err(format!("'weights[{}]' must have {} rows (one per output neuron), got {}", i, outputs, rows.len()));
+            }
+            let rows = rows.iter().enumerate().map(|(j, row)| {
+                let row = parse_numbers(row, &format!("weights[{}][{}]", i, j))?;
+                if row.len() != inputs {
+                    return Err(format!("'weights[{}][{}]' must have {} weights (one per input), got {}", i, j, inputs, row.len()));
+                }
+                Ok(row)
+            }).collect::<Result<Vec<_>, String>>()?;
+            let layer_biases = parse_numbers(layer_biases, &format!("biases[{}]", i))?;
+            if layer_biases.len() != outputs {
+                return Err(format!("'biases[{}]' must have {} values (one per output neuron), got {}", i, outputs, layer_biases.len()));
+            }
+            layers.push(Layer { weights: rows, biases: layer_biases });
+        }
+
+        self.history_length = history_length;
+        self.first_moves = first_moves;
+        self.threshold = threshold;
+        self.layers = layers;
+        Ok(())
+    }
+}
+
+impl fmt::Display for NeuralNetworkStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Neural Network ({} layers)", self.layers.len())
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Neural Network",
+        aliases: &["neuralnetwork", "nn"],
+        description: "Feeds features of the game (last K moves of both players, cooperation rates, turn number and the opponent's first moves) into a feed-forward neural network, and cooperates when its output is above a threshold. The architecture ('layers'), 'weights' and 'biases' must be passed as params.",
+        constructor: || Box::new(NeuralNetworkStrategy::new()),
+        supports_parameters: true,
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{Move, Strategy, trace};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -55,10 +55,12 @@ impl Strategy for OmegaTitForTatStrategy {
         }
         // Check thresholds
         if self.deadlock_count >= self.deadlock_threshold {
+            trace!("breaking deadlock", deadlock_count = self.deadlock_count);
             self.deadlock_count = 0;
             return Move::Cooperate;
         }
         if self.randomness_count >= self.randomness_threshold {
+            trace!("opponent looks random, defecting from now on", randomness_count = self.randomness_count);
             self.unconditional_defection = true;
             return Move::Defect;
         }
@@ -0,0 +1,15 @@
+#!/usr/bin/env python3
+# Tit for Tat as an External strategy (see src/strategies/external.rs for the protocol)
+import json
+import sys
+
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "hello":
+        answer = {"type": "hello", "name": "Python Tit for Tat"}
+    elif message["type"] == "turn":
+        # Only the moves of the previous turn are sent (null on the first turn)
+        answer = {"move": message["opponent_last"] or "C"}
+    else:  # "end"
+        break
+    print(json.dumps(answer), flush=True)
@@ -1,6 +1,5 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
-use rand::Rng;
 use std::fmt;
 
 pub struct ProbabilityCooperatorStrategy {
@@ -17,7 +16,7 @@ impl ProbabilityCooperatorStrategy {
 
 impl Strategy for ProbabilityCooperatorStrategy {
     fn next_move(&mut self, _own_history: &[Move], _opponent_history: &[Move]) -> Move {
-        if rand::rng().random_bool(self.cooperate_probability) {
+        if crate::random_bool(self.cooperate_probability) {
             Move::Cooperate
         } else {
             Move::Defect
@@ -35,6 +34,11 @@ impl Strategy for ProbabilityCooperatorStrategy {
         }
         Err("Invalid parameters. Expected 'cooperate_probability' as a number between 0 and 1".to_string())
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        let p = self.cooperate_probability;
+        Some(MemoryOne { first: p, cc: p, cd: p, dc: p, dd: p })
+    }
 }
 
 impl fmt::Display for ProbabilityCooperatorStrategy {
@@ -0,0 +1,232 @@
+// Implements a tabular Q-learning player that learns within (and across) matches.
+// The state is the last `memory_length` joint moves (own, opponent), the reward is the payoff of
+// each turn, and moves are picked epsilon-greedily (exploration rate decaying over time).
+// The learned Q-table can be exported after a run and passed back as parameters in a later run.
+use crate::{MatchContext, Move, PayoffMatrix, Strategy};
+use crate::strategies::StrategyInfo;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+
+// Longest supported memory (the state is packed in a u64, two bits per joint move)
+const MAX_MEMORY_LENGTH: usize = 31;
+
+pub struct QLearningStrategy {
+    memory_length: usize,
+    learning_rate: f64,
+    discount: f64,
+    exploration_rate: f64,
+    exploration_decay: f64,
+    min_exploration_rate: f64,
+    // Expected return of Cooperate / Defect per state (see `encode_state`)
+    q_table: HashMap<u64, [f64; 2]>,
+    previous_state: Option<u64>,
+    rng: StdRng,
+}
+
+fn action_index(m: Move) -> usize {
+    match m {
+        Move::Cooperate => 0,
+        Move::Defect => 1,
+    }
+}
+
+fn parse_move(c: char) -> Option<Move> {
+    match c {
+        'C' => Some(Move::Cooperate),
+        'D' => Some(Move::Defect),
+        _ => None,
+    }
+}
+
+fn get_probability(params: &serde_json::Value, field: &str, default: f64) -> Result<f64, String> {
+    match params.get(field) {
+        Some(v) => match v.as_f64() {
+            Some(value) if (0.0..=1.0).contains(&value) => Ok(value),
+            _ => Err(format!("Invalid '{}', must be a number between 0 and 1", field)),
+        },
+        None => Ok(default),
+    }
+}
+
+impl QLearningStrategy {
+    pub fn new() -> Self {
+        Self {
+            memory_length: 1,
+            learning_rate: 0.1,
+            discount: 0.9,
+            exploration_rate: 0.1,
+            exploration_decay: 1.0,
+            min_exploration_rate: 0.0,
+            q_table: HashMap::new(),
+            previous_state: None,
+            rng: StdRng::seed_from_u64(crate::with_rng(|rng| rng.next_u64())),
+        }
+    }
+
+    // The last (up to `memory_length`) joint moves, two bits each, after a leading 1
+    // (so that states with fewer moves, at the beginning of a match, are different)
+    fn encode_state(&self, own_history: &[Move], opponent_history: &[Move]) -> u64 {
+        let length = own_history.len().min(opponent_history.len()).min(self.memory_length);
+        let own_recent = &own_history[own_history.len() - length..];
+        let opp_recent = &opponent_history[opponent_history.len() - length..];
+        own_recent.iter().zip(opp_recent).fold(1, |state, (&own, &opp)| {
+            (state << 2) | ((action_index(own) as u64) << 1) | action_index(opp) as u64
+        })
+    }
+
+    // Human readable state, e.g. "CD,DD" (own move first, oldest joint move first). "" is the first turn.
+    fn describe_state(state: u64) -> String {
+        let joint_moves = (63 - state.leading_zeros() as usize) / 2;
+        (0..joint_moves).rev()
+            .map(|i| {
+                let bits = (state >> (2 * i)) & 0b11;
+                let own = if bits & 0b10 == 0 { 'C' } else { 'D' };
+                let opp = if bits & 0b01 == 0 { 'C' } else { 'D' };
+                format!("{}{}", own, opp)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn parse_state(&self, description: &str) -> Result<u64, String> {
+        let mut state = 1;
+        let joint_moves: Vec<&str> = if description.is_empty() { Vec::new() } else { description.split(',').collect() };
+        if joint_moves.len() > self.memory_length {
+            return Err(format!("Q-table state '{}' is longer than 'memory_length' ({})", description, self.memory_length));
+        }
+        for joint in joint_moves {
+            let moves: Vec<Move> = joint.trim().chars().map(parse_move).collect::<Option<_>>()
+                .ok_or(format!("Invalid Q-table state '{}', expected joint moves like 'CD,DD'", description))?;
+            if moves.len() != 2 {
+                return Err(format!("Invalid Q-table state '{}', expected joint moves like 'CD,DD'", description));
+            }
+            state = (state << 2) | ((action_index(moves[0]) as u64) << 1) | action_index(moves[1]) as u64;
+        }
+        Ok(state)
+    }
+
+    fn best_move(&self, state: u64) -> Move {
+        match self.q_table.get(&state) {
+            Some(&[cooperate, defect]) if defect > cooperate => Move::Defect,
+            _ => Move::Cooperate,
+        }
+    }
+
+    // The rewards are the payoffs of the match being played
+    fn play(&mut self, own_history: &[Move], opponent_history: &[Move], payoffs: &PayoffMatrix) -> Move {
+        // Each turn's reward is only known when the next move is asked for, and strategies are not told when a
+        // match ends, so the last turn of every match is never learned (a new match starts from no previous state)
+        if own_history.is_empty() {
+            self.previous_state = None; // New match
+        }
+        let state = self.encode_state(own_history, opponent_history);
+
+        // Learn from the outcome of the last turn
+        if let (Some(previous_state), Some(&last_own), Some(&last_opp)) =
+            (self.previous_state, own_history.last(), opponent_history.last())
+        {
+            let (reward, _) = payoffs.payoffs(last_own, last_opp);
+            let best_next = self.q_table.get(&state).map_or(0.0, |q| q[0].max(q[1]));
+            let q = &mut self.q_table.entry(previous_state).or_insert([0.0; 2])[action_index(last_own)];
+            *q += self.learning_rate * (reward as f64 + self.discount * best_next - *q);
+        }
+        self.previous_state = Some(state);
+
+        let chosen = if self.rng.random_bool(self.exploration_rate) {
+            if self.rng.random_bool(0.5) { Move::Cooperate } else { Move::Defect }
+        } else {
+            self.best_move(state)
+        };
+        self.exploration_rate = (self.exploration_rate * self.exploration_decay).max(self.min_exploration_rate);
+        chosen
+    }
+}
+
+impl Default for QLearningStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Strategy for QLearningStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history, &PayoffMatrix::default())
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        self.play(own_history, opponent_history, &context.payoffs)
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting (all optional): { "memory_length": 1, "learning_rate": 0.1, "discount": 0.9,
+        //   "exploration_rate": 0.1, "exploration_decay": 0.999, "min_exploration_rate": 0.01,
+        //   "seed": 42, "q_table": { "CC": [3.1, 2.5], "CD": [0.4, 1.2], ... } }
+        // Q-table keys are the last joint moves (own move first, oldest first), values are [Cooperate, Defect]
+        if let Some(v) = params.get("memory_length") {
+            let memory_length = v.as_u64().ok_or("Invalid 'memory_length', must be a non-negative integer")? as usize;
+            if memory_length > MAX_MEMORY_LENGTH {
+                return Err(format!("Invalid 'memory_length', must be at most {}", MAX_MEMORY_LENGTH));
+            }
+            self.memory_length = memory_length;
+        }
+        self.learning_rate = get_probability(&params, "learning_rate", self.learning_rate)?;
+        self.discount = get_probability(&params, "discount", self.discount)?;
+        self.exploration_rate = get_probability(&params, "exploration_rate", self.exploration_rate)?;
+        self.exploration_decay = get_probability(&params, "exploration_decay", self.exploration_decay)?;
+        self.min_exploration_rate = get_probability(&params, "min_exploration_rate", self.min_exploration_rate)?;
+        if let Some(seed) = params.get("seed") {
+            let seed = seed.as_u64().ok_or("Invalid 'seed', must be a non-negative integer")?;
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+        if let Some(table) = params.get("q_table") {
+            let table = table.as_object().ok_or("Invalid 'q_table', must be an object")?;
+            let mut q_table = HashMap::new();
+            for (description, values) in table {
+                let state = self.parse_state(description)?;
+                let values = values.as_array()
+                    .filter(|values| values.len() == 2)
+                    .and_then(|values| Some([values[0].as_f64()?, values[1].as_f64()?]))
+                    .ok_or(format!("Invalid Q-table values for '{}', expected [Cooperate, Defect] numbers", description))?;
+                q_table.insert(state, values);
+            }
+            self.q_table = q_table;
+        }
+        Ok(())
+    }
+
+    fn export_parameters(&self) -> Option<serde_json::Value> {
+        let mut states: Vec<_> = self.q_table.iter().collect();
+        states.sort_by_key(|(state, _)| **state);
+        let q_table: serde_json::Map<String, serde_json::Value> = states.into_iter()
+            .map(|(state, values)| (Self::describe_state(*state), json!(values)))
+            .collect();
+        Some(json!({
+            "memory_length": self.memory_length,
+            "learning_rate": self.learning_rate,
+            "discount": self.discount,
+            "exploration_rate": self.exploration_rate,
+            "exploration_decay": self.exploration_decay,
+            "min_exploration_rate": self.min_exploration_rate,
+            "q_table": q_table,
+        }))
+    }
+}
+
+impl fmt::Display for QLearningStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Q-Learning (memory: {})", self.memory_length)
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Q-Learning",
+        aliases: &["qlearning", "q_learning", "ql"],
+        description: "Learns during the match with tabular Q-learning: the state is the last N joint moves, the reward the payoff of each turn (with the match's payoffs). Params: 'memory_length', 'learning_rate', 'discount', 'exploration_rate', 'exploration_decay', 'min_exploration_rate', 'seed' and a previously exported 'q_table'.",
+        constructor: || Box::new(QLearningStrategy::new()),
+        supports_parameters: true,
+    }
+}
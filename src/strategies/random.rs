@@ -1,18 +1,21 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
-use rand::Rng;
 use std::fmt;
 
 pub struct RandomStrategy;
 
 impl Strategy for RandomStrategy {
     fn next_move(&mut self, _own_history: &[Move], _opponent_history: &[Move]) -> Move {
-        if rand::rng().random_bool(0.5) {
+        if crate::random_bool(0.5) {
             Move::Cooperate
         } else {
             Move::Defect
         }
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 0.5, cc: 0.5, cd: 0.5, dc: 0.5, dd: 0.5 })
+    }
 }
 
 impl fmt::Display for RandomStrategy {
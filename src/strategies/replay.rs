@@ -0,0 +1,133 @@
+// Implements the Replay strategy: plays back a recorded sequence of moves, loaded from a raw scores
+// CSV (as written with `--raw-scores-folder`) or from a plain text file of C/D moves.
+// Useful to debug strategies and to reproduce the behaviour of humans or external systems.
+use crate::{MatchContext, Move, Strategy};
+use crate::results::read_moves_from_csv;
+use crate::strategies::StrategyInfo;
+use std::fmt;
+
+// What to do when the recording runs out
+#[derive(Clone, Copy, Debug)]
+enum OnEnd {
+    Loop,
+    Cooperate,
+    Defect,
+    Error,
+}
+
+// Used without a recording, or when "on_end" is not given
+const DEFAULT_ON_END: OnEnd = OnEnd::Error;
+
+pub struct ReplayStrategy {
+    moves: Vec<Move>,
+    source: String,
+    on_end: OnEnd,
+}
+
+impl ReplayStrategy {
+    pub fn new() -> Self {
+        Self { moves: Vec::new(), source: "empty recording".to_string(), on_end: DEFAULT_ON_END }
+    }
+}
+
+impl Default for ReplayStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Plain text recordings: C/D characters (whitespace and commas are ignored, '#' starts a comment line)
+fn read_moves_from_text(contents: &str) -> Result<Vec<Move>, String> {
+    let mut moves = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim_start().starts_with('#')) {
+        for c in line.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+            match c {
+                'C' | 'c' => moves.push(Move::Cooperate),
+                'D' | 'd' => moves.push(Move::Defect),
+                _ => return Err(format!("Invalid character in recording: {}", c)),
+            }
+        }
+    }
+    Ok(moves)
+}
+
+impl ReplayStrategy {
+    fn recorded_move(&self, turn: usize) -> Result<Move, String> {
+        if let Some(&recorded) = self.moves.get(turn) {
+            return Ok(recorded);
+        }
+        match self.on_end {
+            OnEnd::Loop if !self.moves.is_empty() => Ok(self.moves[turn % self.moves.len()]),
+            OnEnd::Loop | OnEnd::Cooperate => Ok(Move::Cooperate),
+            OnEnd::Defect => Ok(Move::Defect),
+            OnEnd::Error => Err(format!(
+                "Replay: the recording from {} has only {} moves, but turn {} was requested",
+                self.source, self.moves.len(), turn + 1
+            )),
+        }
+    }
+}
+
+impl Strategy for ReplayStrategy {
+    fn next_move(&mut self, own_history: &[Move], _opponent_history: &[Move]) -> Move {
+        self.recorded_move(own_history.len()).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], _opponent_history: &[Move], _context: &MatchContext) -> Result<Move, String> {
+        self.recorded_move(own_history.len())
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "file": "results/recording.csv", "column": "Move 1", "on_end": "loop" }
+        // "column" ("Move 1" or "Move 2", only for CSV files) defaults to "Move 1",
+        // "on_end" ("loop", "cooperate", "defect" or "error") defaults to "error"
+        let file = params.get("file")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'file' (raw scores CSV or text file with C/D moves)")?;
+        let column = match params.get("column") {
+            Some(v) => v.as_str().ok_or("Invalid 'column', must be 'Move 1' or 'Move 2'")?,
+            None => "Move 1",
+        };
+        let on_end = match params.get("on_end").map(|v| v.as_str()) {
+            None => DEFAULT_ON_END,
+            Some(Some("error")) => OnEnd::Error,
+            Some(Some("loop")) => OnEnd::Loop,
+            Some(Some("cooperate")) => OnEnd::Cooperate,
+            Some(Some("defect")) => OnEnd::Defect,
+            _ => return Err("Invalid 'on_end', must be 'loop', 'cooperate', 'defect' or 'error'".to_string()),
+        };
+
+        let contents = std::fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read recording '{}': {}", file, e))?;
+        let is_csv = file.to_lowercase().ends_with(".csv") || contents.starts_with("Iteration,");
+        let moves = if is_csv {
+            read_moves_from_csv(&contents, column)?
+        } else {
+            read_moves_from_text(&contents)?
+        };
+        if moves.is_empty() {
+            return Err(format!("The recording '{}' has no moves", file));
+        }
+
+        self.source = if is_csv { format!("'{}' ({})", file, column) } else { format!("'{}'", file) };
+        self.moves = moves;
+        self.on_end = on_end;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReplayStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Replay ({} moves)", self.moves.len())
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Replay",
+        aliases: &["replay"],
+        description: "Plays back the moves recorded in a raw scores CSV ('file' and 'column': 'Move 1' or 'Move 2') or in a text file of C/D moves. 'on_end' sets what happens when the recording runs out: 'loop', 'cooperate', 'defect' or 'error' (default).",
+        constructor: || Box::new(ReplayStrategy::new()),
+        supports_parameters: true,
+    }
+}
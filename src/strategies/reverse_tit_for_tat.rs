@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -17,6 +17,10 @@ impl Strategy for ReverseTitForTatStrategy {
             None => Move::Defect, // First move
         }
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 0.0, cc: 0.0, cd: 1.0, dc: 0.0, dd: 1.0 })
+    }
 }
 
 impl fmt::Display for ReverseTitForTatStrategy {
@@ -0,0 +1,150 @@
+// Implements scripted strategies: the moves are decided by a Rhai (https://rhai.rs) script defining
+//
+//     fn next_move(own, opp) { if opp.len() == 0 { "C" } else { opp.sub_string(opp.len() - 1, 1) } }
+//
+// `own` and `opp` are the histories as strings of C/D (oldest first), and the function returns "C" or "D".
+// Scripts are sandboxed: they cannot access files or the network, and every move has a budget of
+// operations, so a buggy script (e.g. an endless loop) stops the run with an error instead of hanging it.
+// Scripts are registered under a user-chosen name with `register_script` (`--script name=file.rhai`),
+// or used directly as the "Script" strategy with params.
+use crate::{MatchContext, Move, Strategy};
+use crate::strategies::{StrategyInfo, RuntimeStrategyInfo, register_runtime_strategy};
+use rhai::{Engine, EvalAltResult, Scope, AST};
+use rhai::module_resolvers::DummyModuleResolver;
+use std::fmt;
+use std::sync::Arc;
+
+pub const DEFAULT_MAX_OPERATIONS: u64 = 100_000;
+
+pub struct ScriptStrategy {
+    name: String,
+    // Shared by every instance created from the same script
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+}
+
+fn history_string(history: &[Move]) -> String {
+    history.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect()
+}
+
+fn sandboxed_engine(max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(100_000);
+    // No `import` of other script files, nor `eval` of code built at runtime
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    // stdout belongs to the simulator, `print` / `debug` in scripts go to stderr
+    engine.on_print(|text| eprintln!("{}", text));
+    engine.on_debug(|text, _, position| eprintln!("{:?}: {}", position, text));
+    // `trace("reason")` explains a decision (see the `trace` module)
+    engine.register_fn("trace", |reason: &str| crate::trace!(reason));
+    engine
+}
+
+impl ScriptStrategy {
+    pub fn from_file(name: &str, path: &str, max_operations: u64) -> Result<Self, String> {
+        if max_operations == 0 {
+            return Err("The maximum number of operations per move must be > 0".to_string());
+        }
+        let engine = sandboxed_engine(max_operations);
+        let ast = engine.compile_file(path.into())
+            .map_err(|e| format!("Failed to load script '{}': {}", path, e))?;
+        if !ast.iter_functions().any(|f| f.name == "next_move" && f.params.len() == 2) {
+            return Err(format!("Script '{}' must define a 'next_move(own, opp)' function", path));
+        }
+        Ok(Self { name: name.to_string(), engine: Arc::new(engine), ast: Arc::new(ast) })
+    }
+
+    fn request_move(&self, own_history: &[Move], opponent_history: &[Move]) -> Result<Move, String> {
+        let answer: String = self.engine
+            .call_fn(&mut Scope::new(), &self.ast, "next_move", (history_string(own_history), history_string(opponent_history)))
+            .map_err(|e| match *e {
+                EvalAltResult::ErrorTooManyOperations(_) => format!(
+                    "it needed more than {} operations to decide its move (endless loop?)", self.engine.max_operations()
+                ),
+                _ => e.to_string(),
+            })?;
+        match answer.as_str() {
+            "C" | "c" => Ok(Move::Cooperate),
+            "D" | "d" => Ok(Move::Defect),
+            _ => Err(format!("'next_move' returned '{}', expected \"C\" or \"D\"", answer)),
+        }
+    }
+
+    fn play(&self, own_history: &[Move], opponent_history: &[Move]) -> Result<Move, String> {
+        self.request_move(own_history, opponent_history)
+            .map_err(|e| format!("Script strategy '{}' (turn {}): {}", self.name, own_history.len() + 1, e))
+    }
+}
+
+impl Strategy for ScriptStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], _context: &MatchContext) -> Result<Move, String> {
+        self.play(own_history, opponent_history)
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "file": "my_strategy.rhai", "max_operations": 100000 }
+        let file = params.get("file")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'file' (Rhai script defining 'next_move(own, opp)')")?;
+        let max_operations = match params.get("max_operations") {
+            Some(v) => v.as_u64().ok_or("Invalid 'max_operations', must be a positive integer")?,
+            None => DEFAULT_MAX_OPERATIONS,
+        };
+        let name = std::path::Path::new(file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.to_string());
+        *self = Self::from_file(&name, file, max_operations)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ScriptStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Registers the script at `path` as a strategy called `name`, usable anywhere a strategy name is accepted
+pub fn register_script(name: &str, path: &str, max_operations: u64) -> Result<(), String> {
+    let strategy = ScriptStrategy::from_file(name, path, max_operations)?;
+    let (engine, ast) = (strategy.engine, strategy.ast);
+    let strategy_name = name.to_string();
+    register_runtime_strategy(RuntimeStrategyInfo {
+        name: name.to_string(),
+        description: format!("Rhai script '{}' (at most {} operations per move)", path, max_operations),
+        constructor: Box::new(move || Ok(Box::new(ScriptStrategy {
+            name: strategy_name.clone(),
+            engine: Arc::clone(&engine),
+            ast: Arc::clone(&ast),
+        }))),
+        supports_parameters: false,
+    })
+}
+
+// Without params (no script file) it always cooperates
+fn default_script() -> ScriptStrategy {
+    let engine = sandboxed_engine(DEFAULT_MAX_OPERATIONS);
+    let ast = engine.compile(r#"fn next_move(own, opp) { "C" }"#).expect("the default script compiles");
+    ScriptStrategy { name: "Script (no file)".to_string(), engine: Arc::new(engine), ast: Arc::new(ast) }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Script",
+        aliases: &["script", "rhai"],
+        description: "Moves are decided by the 'next_move(own, opp)' function of a Rhai script ('file'), with at most 'max_operations' (default 100000) operations per move. Scripts can also be registered under their own name with --script name=file.rhai.",
+        constructor: || Box::new(default_script()),
+        supports_parameters: true,
+    }
+}
@@ -1,6 +1,5 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
-use rand::Rng;
 use std::fmt;
 
 pub struct StochasticTitForTatStrategy {
@@ -20,7 +19,7 @@ impl Strategy for StochasticTitForTatStrategy {
 
         match opponent_history.last() {
             Some(&last_move) => {
-                if rand::rng().random_bool(self.defection_chance) {
+                if crate::random_bool(self.defection_chance) {
                     Move::Defect
                 } else {
                     last_move
@@ -41,6 +40,11 @@ impl Strategy for StochasticTitForTatStrategy {
         }
         Err("Invalid parameters. Expected 'defection_chance' as a number between 0 and 1".to_string())
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        let p = 1.0 - self.defection_chance;
+        Some(MemoryOne { first: 1.0, cc: p, cd: 0.0, dc: p, dd: 0.0 })
+    }
 }
 
 impl fmt::Display for StochasticTitForTatStrategy {
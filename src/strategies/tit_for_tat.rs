@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -12,6 +12,10 @@ impl Strategy for TitForTatStrategy {
             None => Move::Cooperate, // First move
         }
     }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 1.0, cc: 1.0, cd: 0.0, dc: 1.0, dd: 0.0 })
+    }
 }
 
 impl fmt::Display for TitForTatStrategy {
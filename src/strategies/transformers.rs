@@ -0,0 +1,212 @@
+// Strategy transformers: composable wrappers that change the behaviour of any other strategy.
+// They are created from expressions like `noise(0.05, tit for tat)` or
+// `initial(DD, grudge(2, win-stay, lose-shift))`, anywhere a strategy name is accepted:
+// the first arguments are the transformer's own ones and the last one is the wrapped strategy.
+use crate::{MatchContext, Move, Strategy};
+use crate::simulation::apply_noise;
+use crate::strategies::create_strategy_by_name;
+use crate::strategies::end_game::DefectOnLastTurnsStrategy;
+use std::fmt;
+
+pub struct TransformerInfo {
+    pub name: &'static str,
+    pub syntax: &'static str,
+    pub description: &'static str,
+}
+
+impl fmt::Display for TransformerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (e.g. '{}')", self.name, self.description, self.syntax)
+    }
+}
+
+pub const TRANSFORMERS: &[TransformerInfo] = &[
+    TransformerInfo { name: "flip", syntax: "flip(tit for tat)", description: "Plays the opposite of the wrapped strategy's move." },
+    TransformerInfo { name: "noise", syntax: "noise(0.05, tit for tat)", description: "Flips the wrapped strategy's move with the given probability." },
+    TransformerInfo { name: "initial", syntax: "initial(DDC, tit for tat)", description: "Plays the given sequence of moves first, then the wrapped strategy." },
+    TransformerInfo { name: "final", syntax: "final(3, tit for tat)", description: "Defects on the given number of final turns (when the match length is known)." },
+    TransformerInfo { name: "dual", syntax: "dual(grim trigger)", description: "Mirror behaviour: plays the opposite of what the wrapped strategy would play if its own past moves had been the opposite." },
+    TransformerInfo { name: "rua", syntax: "rua(always cooperate)", description: "Retaliate until apology: defects after every opponent defection, until the opponent cooperates again." },
+    TransformerInfo { name: "grudge", syntax: "grudge(2, tit for tat)", description: "Defects forever once the opponent has defected more than the given number of times." },
+];
+
+enum Transformer {
+    Flip,
+    Noise(f64),
+    Initial(Vec<Move>),
+    Dual,
+    RetaliateUntilApology,
+    Grudge(usize),
+}
+
+pub struct TransformedStrategy {
+    inner: Box<dyn Strategy>,
+    transformer: Transformer,
+    // Own history with every move flipped (only used by `dual`)
+    flipped_history: Vec<Move>,
+    // Defections among the first `counted_opponent_moves` opponent moves (only used by `grudge`)
+    opponent_defections: usize,
+    counted_opponent_moves: usize,
+}
+
+fn flip(m: Move) -> Move {
+    match m {
+        Move::Cooperate => Move::Defect,
+        Move::Defect => Move::Cooperate,
+    }
+}
+
+impl TransformedStrategy {
+    fn new(inner: Box<dyn Strategy>, transformer: Transformer) -> Self {
+        Self { inner, transformer, flipped_history: Vec::new(), opponent_defections: 0, counted_opponent_moves: 0 }
+    }
+
+    // Called before the first move of every match
+    fn reset(&mut self) {
+        self.flipped_history.clear();
+        self.opponent_defections = 0;
+        self.counted_opponent_moves = 0;
+    }
+
+    // Only fails (with a context) if the wrapped strategy does
+    fn play(&mut self, own_history: &[Move], opponent_history: &[Move], context: Option<&MatchContext>) -> Result<Move, String> {
+        if own_history.is_empty() {
+            self.reset();
+        }
+        // Keep the flipped history and the defection count in sync with the histories (which only grow during a match)
+        match self.transformer {
+            Transformer::Dual => {
+                let start = self.flipped_history.len();
+                self.flipped_history.extend(own_history[start..].iter().map(|&m| flip(m)));
+            }
+            Transformer::Grudge(_) => {
+                self.opponent_defections += opponent_history[self.counted_opponent_moves..].iter().filter(|&&m| m == Move::Defect).count();
+                self.counted_opponent_moves = opponent_history.len();
+            }
+            _ => {}
+        }
+        let seen_history = match self.transformer {
+            Transformer::Dual => &self.flipped_history[..],
+            _ => own_history,
+        };
+        // The wrapped strategy always plays, so that its internal state stays up to date
+        let inner_move = match context {
+            Some(context) => self.inner.try_next_move(seen_history, opponent_history, context)?,
+            None => self.inner.next_move(seen_history, opponent_history),
+        };
+
+        Ok(match &self.transformer {
+            Transformer::Flip | Transformer::Dual => flip(inner_move),
+            Transformer::Noise(probability) => apply_noise(inner_move, *probability),
+            Transformer::Initial(sequence) => sequence.get(own_history.len()).copied().unwrap_or(inner_move),
+            Transformer::RetaliateUntilApology => match opponent_history.last() {
+                Some(Move::Defect) => Move::Defect,
+                _ => inner_move,
+            },
+            Transformer::Grudge(threshold) => {
+                if self.opponent_defections > *threshold {
+                    Move::Defect
+                } else {
+                    inner_move
+                }
+            }
+        })
+    }
+}
+
+impl Strategy for TransformedStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history, None).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        self.play(own_history, opponent_history, Some(context)).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        self.play(own_history, opponent_history, Some(context))
+    }
+}
+
+impl fmt::Display for TransformedStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.transformer {
+            Transformer::Flip => write!(f, "Flip({})", self.inner),
+            Transformer::Noise(probability) => write!(f, "Noise({}, {})", probability, self.inner),
+            Transformer::Initial(sequence) => {
+                let sequence: String = sequence.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect();
+                write!(f, "Initial({}, {})", sequence, self.inner)
+            }
+            Transformer::Dual => write!(f, "Dual({})", self.inner),
+            Transformer::RetaliateUntilApology => write!(f, "RUA({})", self.inner),
+            Transformer::Grudge(threshold) => write!(f, "Grudge({}, {})", threshold, self.inner),
+        }
+    }
+}
+
+fn parse_probability(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(probability) if (0.0..=1.0).contains(&probability) => Ok(probability),
+        _ => Err(format!("Invalid probability '{}', must be a number between 0 and 1", arg)),
+    }
+}
+
+fn parse_count(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>().map_err(|_| format!("Invalid number '{}', must be a non-negative integer", arg))
+}
+
+fn parse_sequence(arg: &str) -> Result<Vec<Move>, String> {
+    arg.chars().map(|c| match c {
+        'C' | 'c' => Ok(Move::Cooperate),
+        'D' | 'd' => Ok(Move::Defect),
+        _ => Err(format!("Invalid character in sequence: {}", c)),
+    }).collect()
+}
+
+fn find_transformer(expression: &str) -> Option<(&'static TransformerInfo, usize)> {
+    let open = expression.find('(')?;
+    let transformer_name = expression[..open].trim().to_lowercase();
+    TRANSFORMERS.iter().find(|t| t.name == transformer_name).map(|info| (info, open))
+}
+
+// Whether `expression` is a transformer expression (e.g. `flip(tit for tat)`) rather than a strategy name
+pub fn is_transformer_expression(expression: &str) -> bool {
+    find_transformer(expression.trim()).is_some()
+}
+
+// Builds the strategy described by a transformer expression, e.g. `noise(0.05, tit for tat)`.
+// `params` are passed to the innermost (wrapped) strategy.
+pub fn create_transformed_strategy(expression: &str, params: Option<serde_json::Value>) -> Result<Box<dyn Strategy>, String> {
+    let expression = expression.trim();
+    let (info, open) = find_transformer(expression)
+        .ok_or(format!("'{}' is not a transformer expression", expression))?;
+    if !expression.ends_with(')') {
+        return Err(format!("Missing closing parenthesis in '{}'", expression));
+    }
+    let body = &expression[open + 1..expression.len() - 1];
+
+    // Number of arguments before the wrapped strategy (which may contain commas itself)
+    let arity = match info.name {
+        "flip" | "dual" | "rua" => 0,
+        _ => 1,
+    };
+    let mut parts = body.splitn(arity + 1, ',').map(str::trim);
+    let args: Vec<&str> = parts.by_ref().take(arity).collect();
+    let inner_name = match parts.next() {
+        Some(name) if !name.is_empty() && args.len() == arity => name,
+        _ => return Err(format!("Invalid '{}' transformer, expected something like '{}'", info.name, info.syntax)),
+    };
+
+    let inner = create_strategy_by_name(inner_name, params)?;
+    let strategy: Box<dyn Strategy> = match info.name {
+        "flip" => Box::new(TransformedStrategy::new(inner, Transformer::Flip)),
+        "noise" => Box::new(TransformedStrategy::new(inner, Transformer::Noise(parse_probability(args[0])?))),
+        "initial" => Box::new(TransformedStrategy::new(inner, Transformer::Initial(parse_sequence(args[0])?))),
+        "final" => Box::new(DefectOnLastTurnsStrategy::new(inner, parse_count(args[0])?)),
+        "dual" => Box::new(TransformedStrategy::new(inner, Transformer::Dual)),
+        "rua" => Box::new(TransformedStrategy::new(inner, Transformer::RetaliateUntilApology)),
+        "grudge" => Box::new(TransformedStrategy::new(inner, Transformer::Grudge(parse_count(args[0])?))),
+        _ => unreachable!("every entry of TRANSFORMERS is handled"),
+    };
+    Ok(strategy)
+}
@@ -0,0 +1,269 @@
+// Implements WebAssembly strategy plugins: strategies compiled to `.wasm` modules (or written in the
+// `.wat` text format), loaded at runtime and run sandboxed: they cannot import any host function,
+// every call has a fuel budget (so endless loops stop the run with an error) and their memory is limited.
+//
+// ABI (i32 everywhere) that the modules implement:
+// - `memory`:                               the exported linear memory
+// - `ipd_alloc(len) -> ptr`:                 buffer of `len` bytes where the host writes its input, used until
+//                                            the next `ipd_alloc` call (so it can always be the same one)
+// - `ipd_next_move(own_ptr, opp_ptr, len) -> move`:
+//                                            both histories have `len` bytes, 'C' or 'D' (oldest first),
+//                                            and the result is 0 to cooperate or 1 to defect. The histories stay
+//                                            in the same buffer from one turn to the next, where the host only
+//                                            writes the new moves (so the plugin must not modify them)
+// - `ipd_init(params_ptr, params_len) -> status` (optional):
+//                                            called with `len` 0 when the strategy is created, and again with
+//                                            the JSON params if there are any. Any status other than 0 rejects them
+// - `ipd_reset()` (optional):                called before the first move of every match
+use crate::{MatchContext, Move, Strategy};
+use crate::strategies::{RuntimeStrategyInfo, register_runtime_strategy};
+use std::fmt;
+use std::path::Path;
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TrapCode, TypedFunc};
+
+pub const DEFAULT_FUEL: u64 = 1_000_000;
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+// Moves per player that fit in the first history buffer (it then doubles as needed)
+const MIN_HISTORY_CAPACITY: usize = 256;
+
+// A compiled plugin, from which any number of strategies (each with its own sandbox) can be created
+#[derive(Clone)]
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+pub struct WasmStrategy {
+    name: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    next_move: TypedFunc<(i32, i32, i32), i32>,
+    init: Option<TypedFunc<(i32, i32), i32>>,
+    reset: Option<TypedFunc<(), ()>>,
+    fuel: u64,
+    // Guest buffer with both histories (own moves at `ptr`, the opponent's at `ptr + capacity`) as `(ptr, capacity)`,
+    // and the number of moves of the current match already written to it
+    history_buffer: Option<(i32, usize)>,
+    written_moves: usize,
+}
+
+fn move_bytes(moves: &[Move]) -> Vec<u8> {
+    moves.iter().map(|&m| if m == Move::Cooperate { b'C' } else { b'D' }).collect()
+}
+
+fn call_error(error: wasmi::Error, fuel: u64) -> String {
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => format!("it ran out of fuel (more than {} units for a single call, endless loop?)", fuel),
+        _ => error.to_string(),
+    }
+}
+
+impl WasmPlugin {
+    // Loads the plugin at `path`, named after the file (e.g. `plugins/grim_trigger.wat` is "grim_trigger")
+    pub fn load(path: &Path, fuel: u64, max_memory_bytes: usize) -> Result<Self, String> {
+        if fuel == 0 {
+            return Err("The fuel of the plugins must be > 0".to_string());
+        }
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or(format!("Invalid plugin path '{}'", path.display()))?;
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read plugin '{}': {}", path.display(), e))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes)
+            .map_err(|e| format!("Invalid plugin '{}': {}", path.display(), e))?;
+        if module.imports().next().is_some() {
+            return Err(format!("Invalid plugin '{}': plugins cannot import anything", path.display()));
+        }
+
+        let plugin = Self { name, engine, module, fuel, max_memory_bytes };
+        // Fail now (rather than when the strategy is first used) if the ABI is not implemented
+        plugin.create()
+            .map_err(|e| format!("Invalid plugin '{}': {}", path.display(), e))?;
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn create(&self) -> Result<WasmStrategy, String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_bytes)
+            .instances(1)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+
+        let instance = Linker::new(&self.engine)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(|e| call_error(e, self.fuel))?;
+        let memory = instance.get_memory(&store, "memory").ok_or("Missing exported 'memory'")?;
+        let alloc = instance.get_typed_func(&store, "ipd_alloc")
+            .map_err(|e| format!("Missing or invalid 'ipd_alloc(len: i32) -> i32': {}", e))?;
+        let next_move = instance.get_typed_func(&store, "ipd_next_move")
+            .map_err(|e| format!("Missing or invalid 'ipd_next_move(own: i32, opp: i32, len: i32) -> i32': {}", e))?;
+        // Optional exports, but if present they must have the right signature
+        let init = match instance.get_export(&store, "ipd_init") {
+            Some(_) => Some(instance.get_typed_func(&store, "ipd_init")
+                .map_err(|e| format!("Invalid 'ipd_init(params: i32, len: i32) -> i32': {}", e))?),
+            None => None,
+        };
+        let reset = match instance.get_export(&store, "ipd_reset") {
+            Some(_) => Some(instance.get_typed_func(&store, "ipd_reset")
+                .map_err(|e| format!("Invalid 'ipd_reset()': {}", e))?),
+            None => None,
+        };
+
+        let mut strategy = WasmStrategy {
+            name: self.name.clone(), store, memory, alloc, next_move, init, reset, fuel: self.fuel,
+            history_buffer: None, written_moves: 0,
+        };
+        strategy.initialize(&[])?;
+        Ok(strategy)
+    }
+}
+
+impl WasmStrategy {
+    fn refuel(&mut self) -> Result<(), String> {
+        self.store.set_fuel(self.fuel).map_err(|e| e.to_string())
+    }
+
+    // Asks the plugin for a buffer of `len` bytes. The previous one (e.g. with the histories) is then lost.
+    fn allocate(&mut self, len: usize) -> Result<i32, String> {
+        let len = i32::try_from(len).map_err(|_| "The input is too big for the plugin")?;
+        self.refuel()?;
+        self.history_buffer = None;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(|e| call_error(e, self.fuel))?;
+        if ptr < 0 {
+            return Err(format!("'ipd_alloc' returned an invalid address ({})", ptr));
+        }
+        Ok(ptr)
+    }
+
+    // Copies `data` at `offset` bytes into the buffer at `ptr`
+    fn write_at(&mut self, ptr: i32, offset: usize, data: &[u8]) -> Result<(), String> {
+        self.memory.write(&mut self.store, ptr as usize + offset, data)
+            .map_err(|_| format!("'ipd_alloc' returned an out of bounds buffer ({}, {} bytes needed)", ptr, offset + data.len()))
+    }
+
+    // Copies `data` into a buffer allocated by the plugin and returns its address
+    fn write_input(&mut self, data: &[u8]) -> Result<i32, String> {
+        let ptr = self.allocate(data.len())?;
+        self.write_at(ptr, 0, data)?;
+        Ok(ptr)
+    }
+
+    // Brings the histories in the guest buffer up to date, only writing the moves it does not have yet
+    // (histories only grow during a match), and returns the buffer's `(ptr, capacity)`
+    fn sync_histories(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Result<(i32, usize), String> {
+        let len = own_history.len();
+        if len < self.written_moves {
+            self.written_moves = 0; // Another match
+        }
+        let (ptr, capacity) = match self.history_buffer {
+            Some((ptr, capacity)) if capacity >= len => (ptr, capacity),
+            // Doubling the buffer keeps the copies linear in the number of turns
+            _ => {
+                let capacity = (2 * len).max(MIN_HISTORY_CAPACITY);
+                let ptr = self.allocate(2 * capacity)?;
+                self.written_moves = 0;
+                (ptr, capacity)
+            }
+        };
+        let start = self.written_moves;
+        self.write_at(ptr, start, &move_bytes(&own_history[start..]))?;
+        self.write_at(ptr, capacity + start, &move_bytes(&opponent_history[start..len]))?;
+        self.history_buffer = Some((ptr, capacity));
+        self.written_moves = len;
+        Ok((ptr, capacity))
+    }
+
+    fn initialize(&mut self, params: &[u8]) -> Result<(), String> {
+        let Some(init) = self.init else {
+            return if params.is_empty() { Ok(()) } else { Err("The plugin does not accept parameters (no 'ipd_init')".to_string()) };
+        };
+        let ptr = if params.is_empty() { 0 } else { self.write_input(params)? };
+        self.refuel()?;
+        let status = init.call(&mut self.store, (ptr, params.len() as i32)).map_err(|e| call_error(e, self.fuel))?;
+        if status != 0 {
+            return Err(format!("The plugin rejected its parameters ('ipd_init' returned {})", status));
+        }
+        Ok(())
+    }
+
+    fn request_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Result<Move, String> {
+        if let (true, Some(reset)) = (own_history.is_empty(), self.reset) {
+            self.refuel()?;
+            reset.call(&mut self.store, ()).map_err(|e| call_error(e, self.fuel))?;
+        }
+        let (ptr, capacity) = self.sync_histories(own_history, opponent_history)?;
+        let opp_ptr = i32::try_from(ptr as usize + capacity).map_err(|_| "The histories are too big for the plugin")?;
+        self.refuel()?;
+        match self.next_move.call(&mut self.store, (ptr, opp_ptr, own_history.len() as i32)).map_err(|e| call_error(e, self.fuel))? {
+            0 => Ok(Move::Cooperate),
+            1 => Ok(Move::Defect),
+            other => Err(format!("'ipd_next_move' returned {}, expected 0 (cooperate) or 1 (defect)", other)),
+        }
+    }
+
+    fn play(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Result<Move, String> {
+        self.request_move(own_history, opponent_history)
+            .map_err(|e| format!("Plugin strategy '{}' (turn {}): {}", self.name, own_history.len() + 1, e))
+    }
+}
+
+impl Strategy for WasmStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        self.play(own_history, opponent_history).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], _context: &MatchContext) -> Result<Move, String> {
+        self.play(own_history, opponent_history)
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Any JSON, passed as is to the plugin's `ipd_init`
+        self.initialize(params.to_string().as_bytes())
+            .map_err(|e| format!("Plugin strategy '{}': {}", self.name, e))
+    }
+}
+
+impl fmt::Display for WasmStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Registers every `.wasm` / `.wat` module in `dir` as a strategy named after its file, and returns their names
+pub fn register_plugins_in_dir(dir: &Path, fuel: u64, max_memory_bytes: usize) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read plugin directory '{}': {}", dir.display(), e))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("wasm") | Some("wat")))
+        .collect();
+    paths.sort(); // Same registration (and listing) order on every platform
+
+    let mut names = Vec::with_capacity(paths.len());
+    for path in paths {
+        let plugin = WasmPlugin::load(&path, fuel, max_memory_bytes)?;
+        names.push(plugin.name().to_string());
+        register_runtime_strategy(RuntimeStrategyInfo {
+            name: plugin.name().to_string(),
+            description: format!("WebAssembly plugin '{}'", path.display()),
+            supports_parameters: plugin.create()?.init.is_some(),
+            constructor: Box::new(move || plugin.create().map(|strategy| Box::new(strategy) as Box<dyn Strategy>)),
+        })?;
+    }
+    Ok(names)
+}
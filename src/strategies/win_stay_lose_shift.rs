@@ -1,4 +1,4 @@
-use crate::{Move, Strategy};
+use crate::{MemoryOne, Move, Strategy};
 use crate::strategies::StrategyInfo;
 use std::fmt;
 
@@ -26,6 +26,12 @@ impl Strategy for WinStayLoseShiftStrategy {
             }
         }
     }
+
+    // As implemented above (switching whenever the moves were different), the move after DC is C and
+    // the move after DD is D
+    fn memory_one(&self) -> Option<MemoryOne> {
+        Some(MemoryOne { first: 1.0, cc: 1.0, cd: 0.0, dc: 1.0, dd: 0.0 })
+    }
 }
 
 impl fmt::Display for WinStayLoseShiftStrategy {
@@ -0,0 +1,273 @@
+// Implements the Zero-Determinant (ZD) strategies described by Press & Dyson (2012).
+// A ZD player is a memory-one strategy whose cooperation probabilities (after CC, CD, DC, DD)
+// are chosen so that the long run scores of both players satisfy the linear relation:
+//     S_opponent - baseline = slope * (S_zd - baseline)
+// The probabilities depend on the payoffs, so they are computed again when a match uses other payoffs.
+use crate::{MatchContext, MemoryOne, Move, PayoffMatrix, Strategy};
+use crate::strategies::StrategyInfo;
+use std::fmt;
+
+// Tolerance used when checking that the computed probabilities lie in [0, 1]
+const EPSILON: f64 = 1e-9;
+
+// The baseline can be one of the payoffs, whatever its value in the match
+#[derive(Clone, Copy)]
+enum Baseline {
+    Punishment,
+    Reward,
+    Value(f64),
+}
+
+impl Baseline {
+    fn value(self, payoffs: &PayoffMatrix) -> f64 {
+        match self {
+            Baseline::Punishment => payoffs.punishment as f64,
+            Baseline::Reward => payoffs.reward as f64,
+            Baseline::Value(value) => value,
+        }
+    }
+}
+
+pub struct ZeroDeterminantStrategy {
+    label: String,
+    phi: f64,
+    slope: f64,
+    baseline: Baseline,
+    // Payoffs the probabilities are computed for
+    payoffs: PayoffMatrix,
+    // Cooperation probabilities after (own, opponent) = CC, CD, DC, DD
+    probabilities: [f64; 4],
+}
+
+impl ZeroDeterminantStrategy {
+    // Defaults to ZD-Extort-2 until parameters are provided
+    pub fn new() -> Self {
+        Self::build("Zero Determinant", 1.0 / 9.0, 0.5, Baseline::Punishment, PayoffMatrix::default())
+            .expect("ZD-Extort-2 is feasible with the standard payoff values")
+    }
+
+    // Builds a ZD strategy enforcing `S_opponent - baseline = slope * (S_zd - baseline)` (with the standard payoffs).
+    // `phi` is a scaling factor: any value keeping all four probabilities in [0, 1] is valid.
+    pub fn from_slope(label: &str, phi: f64, slope: f64, baseline: f64) -> Result<Self, String> {
+        Self::build(label, phi, slope, Baseline::Value(baseline), PayoffMatrix::default())
+    }
+
+    // Press & Dyson extortionate form: `S_zd - PUNISHMENT = chi * (S_opponent - PUNISHMENT)`
+    pub fn from_extortion(label: &str, phi: f64, chi: f64) -> Result<Self, String> {
+        if chi < 1.0 {
+            return Err(format!("Invalid ZD parameters: 'chi' must be >= 1 (got {})", chi));
+        }
+        Self::build(label, phi, 1.0 / chi, Baseline::Punishment, PayoffMatrix::default())
+    }
+
+    fn build(label: &str, phi: f64, slope: f64, baseline: Baseline, payoffs: PayoffMatrix) -> Result<Self, String> {
+        let probabilities = Self::compute_probabilities(phi, slope, baseline.value(&payoffs), &payoffs)?;
+        Ok(Self { label: label.to_string(), phi, slope, baseline, payoffs, probabilities })
+    }
+
+    fn compute_probabilities(phi: f64, slope: f64, baseline: f64, payoffs: &PayoffMatrix) -> Result<[f64; 4], String> {
+        let r = payoffs.reward as f64;
+        let t = payoffs.temptation as f64;
+        let s = payoffs.sucker as f64;
+        let p = payoffs.punishment as f64;
+
+        if phi <= 0.0 {
+            return Err(format!("Invalid ZD parameters: 'phi' must be > 0 (got {})", phi));
+        }
+        if baseline < p || baseline > r {
+            return Err(format!(
+                "Invalid ZD parameters: 'baseline' must be between the punishment ({}) and reward ({}) payoffs (got {})",
+                p, r, baseline
+            ));
+        }
+        let min_slope = -((t - baseline) / (baseline - s)).min((baseline - s) / (t - baseline));
+        if slope > 1.0 || slope < min_slope {
+            return Err(format!(
+                "Invalid ZD parameters: 'slope' must be between {:.3} and 1 for baseline {} (got {})",
+                min_slope, baseline, slope
+            ));
+        }
+
+        let probabilities = [
+            1.0 - phi * (1.0 - slope) * (r - baseline),
+            1.0 - phi * (slope * (baseline - s) + (t - baseline)),
+            phi * ((baseline - s) + slope * (t - baseline)),
+            phi * (1.0 - slope) * (baseline - p),
+        ];
+        if probabilities.iter().any(|&prob| !(-EPSILON..=1.0 + EPSILON).contains(&prob)) {
+            return Err(format!(
+                "Invalid ZD parameters: 'phi' {} is too large, the resulting probabilities {:?} are not in [0, 1]",
+                phi, probabilities
+            ));
+        }
+        Ok(probabilities.map(|prob| prob.clamp(0.0, 1.0)))
+    }
+
+    // Computes the probabilities again if the match uses other payoffs. Fails if the strategy is not
+    // feasible with them (e.g. a fixed baseline that is not between the punishment and reward payoffs).
+    fn use_payoffs(&mut self, payoffs: &PayoffMatrix) -> Result<(), String> {
+        if *payoffs != self.payoffs {
+            self.probabilities = Self::compute_probabilities(self.phi, self.slope, self.baseline.value(payoffs), payoffs)
+                .map_err(|e| format!("{} cannot be played with these payoffs: {}", self.label, e))?;
+            self.payoffs = *payoffs;
+        }
+        Ok(())
+    }
+
+    // Cooperation probabilities after (own, opponent) = CC, CD, DC, DD
+    pub fn probabilities(&self) -> [f64; 4] {
+        self.probabilities
+    }
+
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    pub fn baseline(&self) -> f64 {
+        self.baseline.value(&self.payoffs)
+    }
+
+    // Average score per turn the opponent is expected to get (in the long run)
+    // given the average score per turn of this ZD player
+    pub fn predicted_opponent_score(&self, own_score: f64) -> f64 {
+        self.baseline() + self.slope * (own_score - self.baseline())
+    }
+}
+
+impl Default for ZeroDeterminantStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Strategy for ZeroDeterminantStrategy {
+    fn next_move(&mut self, own_history: &[Move], opponent_history: &[Move]) -> Move {
+        let probability = match (own_history.last(), opponent_history.last()) {
+            (Some(Move::Cooperate), Some(Move::Cooperate)) => self.probabilities[0],
+            (Some(Move::Cooperate), Some(Move::Defect))    => self.probabilities[1],
+            (Some(Move::Defect),    Some(Move::Cooperate)) => self.probabilities[2],
+            (Some(Move::Defect),    Some(Move::Defect))    => self.probabilities[3],
+            _ => return Move::Cooperate, // First move
+        };
+        if crate::random_bool(probability) {
+            Move::Cooperate
+        } else {
+            Move::Defect
+        }
+    }
+
+    fn next_move_with_context(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Move {
+        self.try_next_move(own_history, opponent_history, context).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next_move(&mut self, own_history: &[Move], opponent_history: &[Move], context: &MatchContext) -> Result<Move, String> {
+        self.use_payoffs(&context.payoffs)?;
+        Ok(self.next_move(own_history, opponent_history))
+    }
+
+    fn set_parameters(&mut self, params: serde_json::Value) -> Result<(), String> {
+        // Expecting: { "phi": 0.11, "chi": 2 } or { "phi": 0.25, "slope": 0.5, "baseline": 3 }
+        let phi = params.get("phi")
+            .and_then(|v| v.as_f64())
+            .ok_or("Missing or invalid 'phi'")?;
+        let strategy = if let Some(chi) = params.get("chi") {
+            let chi = chi.as_f64().ok_or("Invalid 'chi', must be a number >= 1")?;
+            Self::from_extortion(&self.label, phi, chi)?
+        } else {
+            let slope = params.get("slope")
+                .and_then(|v| v.as_f64())
+                .ok_or("Missing or invalid 'slope' (or provide 'chi' instead)")?;
+            let baseline = params.get("baseline")
+                .and_then(|v| v.as_f64())
+                .ok_or("Missing or invalid 'baseline'")?;
+            Self::from_slope(&self.label, phi, slope, baseline)?
+        };
+        *self = strategy;
+        Ok(())
+    }
+
+    fn memory_one(&self) -> Option<MemoryOne> {
+        let [cc, cd, dc, dd] = self.probabilities;
+        Some(MemoryOne { first: 1.0, cc, cd, dc, dd })
+    }
+}
+
+impl fmt::Display for ZeroDeterminantStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (phi: {:.3}, slope: {:.3}, baseline: {})",
+            self.label, self.phi, self.slope, self.baseline()
+        )
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "Zero Determinant",
+        aliases: &["zerodeterminant", "zd"],
+        description: "Memory-one strategy enforcing a linear relation between both scores: S_opponent - baseline = slope * (S_zd - baseline). Params: 'phi' plus either 'chi' (extortion factor, baseline = PUNISHMENT) or 'slope' and 'baseline'. Defaults to ZD-Extort-2.",
+        constructor: || Box::new(ZeroDeterminantStrategy::new()),
+        supports_parameters: true,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-Extort-2",
+        aliases: &["zdextort2", "zd_extort_2"],
+        description: "Extortionate ZD strategy (phi = 1/9, slope = 1/2, baseline = PUNISHMENT): gets twice the opponent's surplus over mutual defection.",
+        constructor: || Box::new(ZeroDeterminantStrategy::build("ZD-Extort-2", 1.0 / 9.0, 0.5, Baseline::Punishment, PayoffMatrix::default()).expect("ZD-Extort-2 is feasible")),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-Extort-2 v2",
+        aliases: &["zdextort2v2", "zd_extort_2_v2"],
+        description: "Extortionate ZD strategy (phi = 1/8, slope = 1/2, baseline = PUNISHMENT): same relation as ZD-Extort-2 but less likely to cooperate.",
+        constructor: || Box::new(ZeroDeterminantStrategy::build("ZD-Extort-2 v2", 1.0 / 8.0, 0.5, Baseline::Punishment, PayoffMatrix::default()).expect("ZD-Extort-2 v2 is feasible")),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-Extort-4",
+        aliases: &["zdextort4", "zd_extort_4"],
+        description: "Extortionate ZD strategy (phi = 4/17, slope = 1/4, baseline = PUNISHMENT): gets four times the opponent's surplus over mutual defection.",
+        constructor: || Box::new(ZeroDeterminantStrategy::build("ZD-Extort-4", 4.0 / 17.0, 0.25, Baseline::Punishment, PayoffMatrix::default()).expect("ZD-Extort-4 is feasible")),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-GTFT-2",
+        aliases: &["zdgtft2", "zd_gtft_2"],
+        description: "Generous ZD strategy (phi = 1/4, slope = 1/2, baseline = REWARD): forgiving, the opponent's shortfall from mutual cooperation is half of its own.",
+        constructor: || Box::new(ZeroDeterminantStrategy::build("ZD-GTFT-2", 0.25, 0.5, Baseline::Reward, PayoffMatrix::default()).expect("ZD-GTFT-2 is feasible")),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-GEN-2",
+        aliases: &["zdgen2", "zd_gen_2"],
+        description: "Generous ZD strategy (phi = 1/8, slope = 1/2, baseline = REWARD): like ZD-GTFT-2 but less forgiving.",
+        constructor: || Box::new(ZeroDeterminantStrategy::build("ZD-GEN-2", 1.0 / 8.0, 0.5, Baseline::Reward, PayoffMatrix::default()).expect("ZD-GEN-2 is feasible")),
+        supports_parameters: false,
+    }
+}
+
+inventory::submit! {
+    StrategyInfo {
+        name: "ZD-SET-2",
+        aliases: &["zdset2", "zd_set_2"],
+        description: "Equalizer ZD strategy (phi = 1/4, slope = 0, baseline = 2): unilaterally sets the opponent's average score to 2, whatever it plays.",
+        constructor: || Box::new(ZeroDeterminantStrategy::from_slope("ZD-SET-2", 0.25, 0.0, 2.0).expect("ZD-SET-2 is feasible")),
+        supports_parameters: false,
+    }
+}
@@ -0,0 +1,91 @@
+// Per-decision tracing: strategies explain their decisions with the `trace!` macro, e.g.
+//
+//     trace!("breaking deadlock", deadlock_count = self.deadlock_count);
+//
+// and the match runner tags every reason with the turn and the player that was deciding. After each turn,
+// `take_reasons` (e.g. in `play_match`'s `on_turn`) returns the reasons given during that turn. Tracing is
+// off unless enabled (per thread, like the seeded random numbers) with `set_tracing`. While it is off,
+// `trace!` only reads a flag: its message and fields are neither formatted nor stored.
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+
+// A reason given by a strategy for one of its decisions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reason {
+    pub turn: usize,
+    // 1 or 2 (0 if given outside a match)
+    pub player: usize,
+    pub message: String,
+    // Values (e.g. counters or averages) the decision was based on
+    pub fields: Vec<(&'static str, Value)>,
+}
+
+thread_local! {
+    static TRACING: Cell<bool> = const { Cell::new(false) };
+    // Turn and player currently deciding, and the reasons given during the current turn
+    static DECISION: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    static REASONS: RefCell<Vec<Reason>> = const { RefCell::new(Vec::new()) };
+}
+
+// Turns tracing on (or off) on the current thread
+pub fn set_tracing(enabled: bool) {
+    TRACING.with(|tracing| tracing.set(enabled));
+    REASONS.with(|reasons| reasons.borrow_mut().clear());
+}
+
+pub fn is_tracing() -> bool {
+    TRACING.with(Cell::get)
+}
+
+// Called by the match runner before asking `player` (1 or 2) for its move. A new turn drops the reasons
+// of the previous one that nobody took.
+pub fn begin_decision(turn: usize, player: usize) {
+    if !is_tracing() {
+        return;
+    }
+    if player == 1 {
+        REASONS.with(|reasons| reasons.borrow_mut().clear());
+    }
+    DECISION.with(|decision| decision.set((turn, player)));
+}
+
+// Records a reason of the player deciding (use `trace!`, which does nothing when tracing is off)
+pub fn record(message: impl Into<String>, fields: Vec<(&'static str, Value)>) {
+    let (turn, player) = DECISION.with(Cell::get);
+    let reason = Reason { turn, player, message: message.into(), fields };
+    REASONS.with(|reasons| reasons.borrow_mut().push(reason));
+}
+
+// The reasons given since the current turn started
+pub fn take_reasons() -> Vec<Reason> {
+    REASONS.with(|reasons| std::mem::take(&mut *reasons.borrow_mut()))
+}
+
+// A field value (as JSON, `null` if it cannot be represented)
+pub fn field_value<T: Serialize + ?Sized>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+impl Reason {
+    // "breaking deadlock (deadlock_count=3)"
+    pub fn describe(&self) -> String {
+        if self.fields.is_empty() {
+            return self.message.clone();
+        }
+        let fields: Vec<String> = self.fields.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        format!("{} ({})", self.message, fields.join(", "))
+    }
+}
+
+// Gives the reason of a strategy's decision, with optional `name = value` fields (any `Serialize` value):
+//
+//     trace!("opponent defected", retaliation = self.retaliation);
+#[macro_export]
+macro_rules! trace {
+    ($message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        if $crate::trace::is_tracing() {
+            $crate::trace::record($message, vec![$((stringify!($field), $crate::trace::field_value(&$value))),*]);
+        }
+    };
+}
@@ -0,0 +1,52 @@
+use iterated_prisoners_dilemma_lib::analysis::{compare_with_simulation, exact_payoffs_for};
+use iterated_prisoners_dilemma_lib::{PayoffMatrix, seed_rng};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+
+#[test]
+fn exact_payoffs_match_long_simulations() {
+    let matchups = [
+        ("Generous Tit for Tat", "Win-Stay, Lose-Shift", 0.05),
+        ("Tit for Tat", "Probability Cooperator", 0.0),
+        ("ZD-Extort-2", "Tit for Tat", 0.02),
+        ("Win-Stay, Lose-Shift", "Random", 0.0),
+        ("Reverse Tit for Tat", "Stochastic Tit for Tat", 0.01),
+        ("Suspicious Tit for Tat", "Generous Tit for Tat", 0.05),
+    ];
+    seed_rng(Some(27));
+    for (name1, name2, noise) in matchups {
+        let mut strategy1 = create_strategy_by_name(name1, None).unwrap();
+        let mut strategy2 = create_strategy_by_name(name2, None).unwrap();
+        let comparison = compare_with_simulation(strategy1.as_mut(), strategy2.as_mut(), 100_000, noise, &PayoffMatrix::default()).unwrap();
+        let (exact1, exact2) = (comparison.exact.payoff1, comparison.exact.payoff2);
+        assert!(
+            (comparison.simulated1 - exact1).abs() < 0.02 && (comparison.simulated2 - exact2).abs() < 0.02,
+            "{} vs {} (noise {}): simulated {:.4} / {:.4}, exact {:.4} / {:.4}",
+            name1, name2, noise, comparison.simulated1, comparison.simulated2, exact1, exact2
+        );
+    }
+    seed_rng(None);
+}
+
+#[test]
+fn exact_payoffs_use_the_given_payoffs() {
+    let payoffs = PayoffMatrix { reward: 4, temptation: 7, sucker: -1, punishment: 2 };
+    let cooperator = create_strategy_by_name("Always Cooperate", None).unwrap();
+    let defector = create_strategy_by_name("Always Defect", None).unwrap();
+    let exact = exact_payoffs_for(cooperator.as_ref(), defector.as_ref(), 0.0, &payoffs).unwrap();
+    assert_eq!((exact.payoff1, exact.payoff2), (-1.0, 7.0));
+
+    // Including strategies whose probabilities depend on the payoffs
+    seed_rng(Some(27));
+    for (name1, name2, noise) in [("Generous Tit for Tat", "Win-Stay, Lose-Shift", 0.05), ("ZD-Extort-2", "Random", 0.0)] {
+        let mut strategy1 = create_strategy_by_name(name1, None).unwrap();
+        let mut strategy2 = create_strategy_by_name(name2, None).unwrap();
+        let comparison = compare_with_simulation(strategy1.as_mut(), strategy2.as_mut(), 100_000, noise, &payoffs).unwrap();
+        let (exact1, exact2) = (comparison.exact.payoff1, comparison.exact.payoff2);
+        assert!(
+            (comparison.simulated1 - exact1).abs() < 0.03 && (comparison.simulated2 - exact2).abs() < 0.03,
+            "{} vs {}: simulated {:.4} / {:.4}, exact {:.4} / {:.4}",
+            name1, name2, comparison.simulated1, comparison.simulated2, exact1, exact2
+        );
+    }
+    seed_rng(None);
+}
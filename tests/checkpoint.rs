@@ -0,0 +1,101 @@
+use iterated_prisoners_dilemma_lib::checkpoint::Checkpoint;
+use iterated_prisoners_dilemma_lib::experiment::{Experiment, MatchSummary, RunControl};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const EXPERIMENT: &str = r#"
+turns = 200
+noise = 0.1
+seed = 11
+repetitions = 2
+
+[[players]]
+strategy = "Random"
+
+[[players]]
+strategy = "Tit for Tat"
+
+[[players]]
+strategy = "noise(0.2, grim trigger)"
+"#;
+
+fn state_file(name: &str) -> String {
+    std::env::temp_dir().join(format!("ipd-checkpoint-{}-{}.jsonl", name, std::process::id())).display().to_string()
+}
+
+fn totals(summaries: &[MatchSummary]) -> Vec<(usize, usize, usize, i64, i64)> {
+    summaries.iter()
+        .map(|summary| (summary.repetition, summary.player1, summary.player2, summary.result.total1, summary.result.total2))
+        .collect()
+}
+
+// Runs the experiment with a checkpoint, interrupting it once `matches` matches are completed
+fn interrupted_run(experiment: &Experiment, path: &str, matches: usize) -> String {
+    let interrupted = AtomicBool::new(false);
+    let control = RunControl { checkpoint: Some(Checkpoint::create(path, experiment).unwrap()), interrupted: Some(&interrupted), progress: None };
+    let mut completed = 0;
+    let error = experiment.run_with(control, |_| {
+        completed += 1;
+        if completed == matches {
+            interrupted.store(true, Ordering::SeqCst);
+        }
+    }).err().unwrap();
+    assert_eq!(completed, matches);
+    error
+}
+
+#[test]
+fn resumed_runs_match_uninterrupted_ones() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let uninterrupted = totals(&experiment.run(|_| {}).unwrap());
+    assert_eq!(uninterrupted.len(), 6);
+
+    let path = state_file("resume");
+    let error = interrupted_run(&experiment, &path, 4);
+    assert_eq!(error, "Interrupted after 4 of 6 matches");
+
+    let checkpoint = Checkpoint::resume(&path, &experiment).unwrap();
+    assert_eq!(checkpoint.completed(), 4);
+    let mut played = 0;
+    let control = RunControl { checkpoint: Some(checkpoint), interrupted: None, progress: None };
+    let resumed = experiment.run_with(control, |_| played += 1).unwrap();
+    // Completed matches are reported again, but not played again
+    assert_eq!(played, 6);
+    assert_eq!(totals(&resumed), uninterrupted);
+
+    // The state file now has every match
+    let checkpoint = Checkpoint::resume(&path, &experiment).unwrap();
+    assert_eq!(checkpoint.completed(), 6);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn a_line_cut_by_a_crash_is_ignored() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let path = state_file("crash");
+    interrupted_run(&experiment, &path, 2);
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    write!(file, r#"{{"repetition":1,"player1":1,"player2":2,"moves1":"CCD"#).unwrap();
+    drop(file);
+
+    let checkpoint = Checkpoint::resume(&path, &experiment).unwrap();
+    assert_eq!(checkpoint.completed(), 2);
+    let control = RunControl { checkpoint: Some(checkpoint), interrupted: None, progress: None };
+    experiment.run_with(control, |_| {}).unwrap();
+    assert_eq!(Checkpoint::resume(&path, &experiment).unwrap().completed(), 6);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn state_files_only_resume_their_experiment() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let path = state_file("other");
+    interrupted_run(&experiment, &path, 1);
+    assert_eq!(Checkpoint::read_experiment(&path).unwrap().to_toml(), experiment.to_toml());
+
+    let other = Experiment::parse(&EXPERIMENT.replace("seed = 11", "seed = 12"), "other.toml").unwrap();
+    let error = Checkpoint::resume(&path, &other).err().unwrap();
+    assert!(error.contains("different experiment"), "{}", error);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "sqlite")]
+use iterated_prisoners_dilemma_lib::db::{ResultStore, RunRecord, ScoreFilter};
+use iterated_prisoners_dilemma_lib::experiment::{MatchSummary, Player};
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, MatchResult};
+use iterated_prisoners_dilemma_lib::Move::{Cooperate as C, Defect as D};
+
+fn player(label: &str, strategy: &str) -> Player {
+    Player { label: label.to_string(), strategy: strategy.to_string(), params: None }
+}
+
+fn record(store: &mut ResultStore, noise: f64, players: &[Player], result: MatchResult) {
+    let config = MatchConfig::new(result.turns()).with_noise(noise);
+    let run = RunRecord { command: "match", name: None, config: &config, seed: None, repetitions: 1, configuration: None, players };
+    store.record_run(&run, &[MatchSummary { repetition: 1, player1: 0, player2: 1, result }], true).unwrap();
+}
+
+#[test]
+fn mean_score_filters_by_opponent_and_noise() {
+    let path = std::env::temp_dir().join(format!("ipd-db-test-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut store = ResultStore::open(path.to_str().unwrap()).unwrap();
+
+    let tft_vs_alld = [player("Tit for Tat", "tft"), player("Always Defect", "alld")];
+    record(&mut store, 0.1, &tft_vs_alld, MatchResult { history1: vec![C, D], history2: vec![D, D], total1: 1, total2: 6 });
+    record(&mut store, 0.0, &tft_vs_alld, MatchResult { history1: vec![C, D], history2: vec![D, D], total1: 1, total2: 6 });
+    // Tit for Tat as the second player
+    let allc_vs_tft = [player("Always Cooperate", "allc"), player("Tit for Tat", "tft")];
+    record(&mut store, 0.1, &allc_vs_tft, MatchResult { history1: vec![C, C], history2: vec![C, C], total1: 6, total2: 6 });
+
+    let filter = |opponent: Option<&str>, noise_above: Option<f64>| ScoreFilter {
+        player: "tit for tat".to_string(),
+        opponent: opponent.map(String::from),
+        noise_above,
+        noise_below: None,
+    };
+    let all = store.mean_score(&filter(None, None)).unwrap().unwrap();
+    assert_eq!((all.matches, all.runs), (3, 3));
+    assert!((all.score - 8.0 / 3.0).abs() < 1e-9);
+
+    // Strategy names match too
+    let noisy = store.mean_score(&filter(Some("ALLD"), Some(0.0))).unwrap().unwrap();
+    assert_eq!((noisy.matches, noisy.score, noisy.opponent_score), (1, 1.0, 6.0));
+    assert_eq!(noisy.cooperation_rate, 0.5);
+
+    assert!(store.mean_score(&filter(Some("Random"), None)).unwrap().is_none());
+    assert_eq!(store.query("SELECT COUNT(*) FROM moves").unwrap().rows, vec![vec!["6".to_string()]]);
+
+    drop(store);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,48 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::{Move, Strategy};
+use serde_json::json;
+
+// Moves of `player` against Always Cooperate, as a string of C/D
+fn moves(player: &mut dyn Strategy, config: &MatchConfig) -> String {
+    let mut cooperator = create_strategy_by_name("Always Cooperate", None).unwrap();
+    let result = play_match(player, cooperator.as_mut(), config, |_| {}).unwrap();
+    result.history1.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect()
+}
+
+#[test]
+fn defects_on_exactly_the_last_turns() {
+    let params = json!({ "strategy": "Always Cooperate", "turns": 3 });
+    let mut end_game = create_strategy_by_name("Defect On Last Turns", Some(params)).unwrap();
+    assert_eq!(moves(end_game.as_mut(), &MatchConfig::new(8)), "CCCCCDDD");
+    assert_eq!(moves(end_game.as_mut(), &MatchConfig::new(2)), "DD");
+}
+
+#[test]
+fn plays_the_wrapped_strategy_when_the_length_is_unknown() {
+    let params = json!({ "strategy": "Always Cooperate", "turns": 3 });
+    let mut end_game = create_strategy_by_name("Defect On Last Turns", Some(params)).unwrap();
+    assert_eq!(moves(end_game.as_mut(), &MatchConfig::new(8).with_length_known(false)), "CCCCCCCC");
+}
+
+#[test]
+fn end_game_tit_for_tat_defects_on_the_last_2_turns() {
+    let mut end_game = create_strategy_by_name("End Game Tit for Tat", None).unwrap();
+    assert_eq!(moves(end_game.as_mut(), &MatchConfig::new(6)), "CCCCDD");
+
+    // Otherwise it is Tit for Tat
+    let mut end_game = create_strategy_by_name("End Game Tit for Tat", None).unwrap();
+    let mut alternating = create_strategy_by_name("Cycler", Some(json!({ "sequence": "DC" }))).unwrap();
+    let result = play_match(end_game.as_mut(), alternating.as_mut(), &MatchConfig::new(6), |_| {}).unwrap();
+    assert_eq!(result.history1[1..4], result.history2[..3]);
+    assert_eq!(result.history1[4..], [Move::Defect, Move::Defect]);
+}
+
+#[test]
+fn invalid_params_are_rejected() {
+    let error = |params| create_strategy_by_name("Defect On Last Turns", Some(params)).err().unwrap();
+    assert!(error(json!({ "turns": 2 })).contains("Missing or invalid 'strategy'"));
+    assert!(error(json!({ "strategy": "Tit for Tat" })).contains("Missing or invalid 'turns'"));
+    assert!(error(json!({ "strategy": "Tit for Tat", "turns": -1 })).contains("Missing or invalid 'turns'"));
+    assert!(error(json!({ "strategy": "No Such Strategy", "turns": 2 })).contains("Unknown strategy"));
+}
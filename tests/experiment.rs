@@ -0,0 +1,85 @@
+use iterated_prisoners_dilemma_lib::experiment::{Experiment, RunControl};
+use iterated_prisoners_dilemma_lib::progress::{Progress, ProgressStyle};
+
+const EXPERIMENT: &str = r#"
+turns = 50
+noise = 0.1
+seed = 7
+repetitions = 2
+
+[[players]]
+strategy = "Random"
+
+[[players]]
+label = "Generous"
+strategy = "Configurable Generous Tit for Tat"
+params = { cooperation_probability = 0.3 }
+
+[[players]]
+strategy = "noise(0.2, tit for tat)"
+"#;
+
+fn totals(experiment: &Experiment) -> Vec<(i64, i64)> {
+    experiment.run(|_| {}).unwrap().iter().map(|summary| (summary.result.total1, summary.result.total2)).collect()
+}
+
+#[test]
+fn seeded_experiments_are_reproducible() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    assert_eq!(experiment.matchups(), vec![(0, 1), (0, 2), (1, 2)]);
+
+    let first = totals(&experiment);
+    assert_eq!(first.len(), 6); // 3 matchups, 2 repetitions
+    assert_eq!(first, totals(&experiment));
+    // Each repetition has its own seed
+    assert_ne!(first[..3], first[3..]);
+}
+
+#[test]
+fn errors_point_to_the_offending_line() {
+    let invalid_params = EXPERIMENT.replace("cooperation_probability = 0.3", "cooperation_probability = 3.0");
+    let error = Experiment::parse(&invalid_params, "experiment.toml").unwrap_err();
+    assert!(error.starts_with("experiment.toml:13: "), "{}", error);
+
+    let unknown_field = EXPERIMENT.replace("repetitions = 2", "repetitionz = 2");
+    let error = Experiment::parse(&unknown_field, "experiment.toml").unwrap_err();
+    assert!(error.starts_with("experiment.toml:5: "), "{}", error);
+}
+
+#[test]
+fn resolved_config_is_valid_toml() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let echoed = Experiment::parse(&experiment.to_toml(), "echoed.toml").unwrap();
+    assert_eq!(echoed.to_toml(), experiment.to_toml());
+}
+
+#[test]
+fn average_payoffs_cover_both_sides_of_every_matchup() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let summaries = experiment.run(|_| {}).unwrap();
+    let averages = experiment.average_payoffs(&summaries);
+
+    for (player, row) in averages.iter().enumerate() {
+        for (opponent, average) in row.iter().enumerate() {
+            // Nobody plays against itself
+            assert_eq!(average.is_none(), player == opponent);
+        }
+    }
+    let (total1, total2) = summaries.iter()
+        .filter(|summary| (summary.player1, summary.player2) == (0, 1))
+        .fold((0, 0), |(total1, total2), summary| (total1 + summary.result.total1, total2 + summary.result.total2));
+    assert_eq!(averages[0][1], Some(total1 as f64 / 100.0)); // 2 repetitions of 50 turns
+    assert_eq!(averages[1][0], Some(total2 as f64 / 100.0));
+}
+
+#[test]
+fn progress_display_does_not_change_results() {
+    let experiment = Experiment::parse(EXPERIMENT, "experiment.toml").unwrap();
+    let mut progress = Progress::new(ProgressStyle::Json, 6, experiment.turns);
+    let control = RunControl { progress: Some(&mut progress), ..Default::default() };
+    let summaries = experiment.run_with(control, |_| {}).unwrap();
+    progress.finish();
+
+    let with_progress: Vec<(i64, i64)> = summaries.iter().map(|summary| (summary.result.total1, summary.result.total2)).collect();
+    assert_eq!(with_progress, totals(&experiment));
+}
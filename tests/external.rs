@@ -0,0 +1,58 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::json;
+
+// A Python program answering the handshake, then running `on_turn` for every turn message
+fn python_strategy(on_turn: &str) -> serde_json::Value {
+    let program = format!(
+        "import json, sys\n\
+         for line in sys.stdin:\n    \
+             message = json.loads(line)\n    \
+             if message['type'] == 'hello':\n        \
+                 print(json.dumps({{'type': 'hello'}}), flush=True)\n    \
+             elif message['type'] == 'turn':\n        \
+                 {}\n    \
+             else:\n        \
+                 break\n",
+        on_turn
+    );
+    json!({ "command": "python3", "args": ["-c", program] })
+}
+
+#[test]
+fn the_example_plays_tit_for_tat() {
+    let params = json!({ "command": "python3", "args": ["src/strategies/parameter_examples/external_tit_for_tat.py"] });
+    let mut external = create_strategy_by_name("External", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Tester", None).unwrap();
+    let result = play_match(external.as_mut(), opponent.as_mut(), &MatchConfig::new(6), |_| {}).unwrap();
+    let mut tester = create_strategy_by_name("Tester", None).unwrap();
+    let mut tit_for_tat = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let expected = play_match(tit_for_tat.as_mut(), tester.as_mut(), &MatchConfig::new(6), |_| {}).unwrap();
+    assert_eq!((result.total1, result.total2), (expected.total1, expected.total2));
+}
+
+#[test]
+fn full_histories_are_only_sent_on_request() {
+    // Cooperates as long as the message has the history of the previous turns
+    let answer = "print(json.dumps({'move': 'C' if len(message.get('opponent_history', '')) == message['turn'] - 1 else 'D'}), flush=True)";
+    let mut opponent = create_strategy_by_name("Always Cooperate", None).unwrap();
+
+    let mut params = python_strategy(answer);
+    let mut external = create_strategy_by_name("External", Some(params.clone())).unwrap();
+    let result = play_match(external.as_mut(), opponent.as_mut(), &MatchConfig::new(3), |_| {}).unwrap();
+    // Only the first turn (with an empty history) looks right
+    assert_eq!(result.total1, 3 + 5 + 5);
+
+    params["full_history"] = json!(true);
+    let mut external = create_strategy_by_name("External", Some(params)).unwrap();
+    let result = play_match(external.as_mut(), opponent.as_mut(), &MatchConfig::new(3), |_| {}).unwrap();
+    assert_eq!(result.total1, 3 * 3);
+}
+
+#[test]
+fn a_crash_stops_the_match_with_an_error() {
+    let mut external = create_strategy_by_name("External", Some(python_strategy("sys.exit(3)"))).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let error = play_match(external.as_mut(), opponent.as_mut(), &MatchConfig::new(5), |_| {}).err().unwrap();
+    assert!(error.contains("(turn 1)") && error.contains("exited"), "{}", error);
+}
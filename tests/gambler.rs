@@ -0,0 +1,45 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::{Move, seed_rng};
+use serde_json::{Value, json};
+
+fn entry(own: &str, opp: &str, probability: f64) -> Value {
+    json!({ "own": [own], "opp": [opp], "probability": probability })
+}
+
+// Tit for Tat as a Gambler: certain to repeat the opponent's last move
+fn tit_for_tat_table() -> Vec<Value> {
+    vec![entry("C", "C", 1.0), entry("C", "D", 0.0), entry("D", "C", 1.0), entry("D", "D", 0.0)]
+}
+
+#[test]
+fn probabilities_of_0_and_1_give_deterministic_moves() {
+    let params = json!({ "history_length": 1, "lookup_table": tit_for_tat_table() });
+    let mut gambler = create_strategy_by_name("Gambler", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Random", None).unwrap();
+    seed_rng(Some(28));
+    let result = play_match(gambler.as_mut(), opponent.as_mut(), &MatchConfig::new(200), |_| {}).unwrap();
+    seed_rng(None);
+
+    assert_eq!(result.history1[0], Move::Cooperate);
+    assert_eq!(result.history1[1..], result.history2[..199]);
+}
+
+#[test]
+fn duplicated_entries_are_rejected() {
+    let mut table = tit_for_tat_table();
+    table.push(entry("D", "C", 0.5));
+    let error = create_strategy_by_name("Gambler", Some(json!({ "history_length": 1, "lookup_table": table }))).err().unwrap();
+    assert!(error.contains("Duplicated lookup table entry (own: D, opp: C)"), "{}", error);
+}
+
+#[test]
+fn incomplete_tables_need_a_default() {
+    let mut table = tit_for_tat_table();
+    table.pop();
+    let error = create_strategy_by_name("Gambler", Some(json!({ "history_length": 1, "lookup_table": table.clone() }))).err().unwrap();
+    assert!(error.contains("1 of 4 entries are missing (e.g. own: D, opp: D)"), "{}", error);
+
+    let params = json!({ "history_length": 1, "lookup_table": table, "default": 0.0 });
+    assert!(create_strategy_by_name("Gambler", Some(params)).is_ok());
+}
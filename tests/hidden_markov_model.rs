@@ -0,0 +1,39 @@
+use iterated_prisoners_dilemma_lib::{Move, seed_rng};
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::json;
+
+// The HMM Moody preset, seeded through the thread's RNG (which it draws its own seed from when created)
+fn moody_against_tit_for_tat(seed: u64) -> Vec<Move> {
+    seed_rng(Some(seed));
+    let mut hmm = create_strategy_by_name("HMM Moody", None).unwrap();
+    seed_rng(None);
+    let mut tit_for_tat = create_strategy_by_name("Tit for Tat", None).unwrap();
+    play_match(hmm.as_mut(), tit_for_tat.as_mut(), &MatchConfig::new(200), |_| {}).unwrap().history1
+}
+
+#[test]
+fn seeded_runs_are_reproducible() {
+    assert_eq!(moody_against_tit_for_tat(30), moody_against_tit_for_tat(30));
+    assert_ne!(moody_against_tit_for_tat(30), moody_against_tit_for_tat(31));
+}
+
+#[test]
+fn rows_must_add_up_to_1() {
+    let two_states = json!({
+        "transitions_c": [[0.9, 0.1], [0.5, 0.5]],
+        "transitions_d": [[0.2, 0.8], [0.0, 1.0]],
+        "emissions": [0.9, 0.1],
+        "initial_distribution": [1.0, 0.0]
+    });
+
+    let mut params = two_states.clone();
+    params["transitions_d"][1] = json!([0.0, 0.9]);
+    let error = create_strategy_by_name("Hidden Markov Model", Some(params)).err().unwrap();
+    assert!(error.contains("'transitions_d[1]' must add up to 1"), "{}", error);
+
+    let mut params = two_states;
+    params["initial_distribution"] = json!([0.5, 0.6]);
+    let error = create_strategy_by_name("Hidden Markov Model", Some(params)).err().unwrap();
+    assert!(error.contains("'initial_distribution' must add up to 1"), "{}", error);
+}
@@ -0,0 +1,99 @@
+use iterated_prisoners_dilemma_lib::history::{convert_history, read_history, HistoryHeader, HistoryReader, HistoryWriter, BLOCK_TURNS};
+use iterated_prisoners_dilemma_lib::{Move, PayoffMatrix};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ipd-history-test-{}-{}", std::process::id(), name))
+}
+
+// A deterministic but irregular sequence of moves
+fn moves(turns: u64) -> Vec<(Move, Move)> {
+    (0..turns)
+        .map(|turn| {
+            let to_move = |defect: bool| if defect { Move::Defect } else { Move::Cooperate };
+            (to_move(turn % 3 == 0), to_move(turn % 7 < 2))
+        })
+        .collect()
+}
+
+#[test]
+fn binary_history_supports_random_access() {
+    let path = temp_path("random-access.ipdh");
+    let path = path.to_str().unwrap();
+    let turns = 2 * BLOCK_TURNS + 5;
+    let all = moves(turns);
+    let mut header = HistoryHeader::new("Tit for Tat", "Random", PayoffMatrix::default());
+    header.seed = Some(42);
+
+    let mut writer = HistoryWriter::create(path, &header).unwrap();
+    for &(move1, move2) in &all {
+        writer.push(move1, move2).unwrap();
+    }
+    assert_eq!(writer.finish().unwrap(), turns);
+    // 2 bits per turn, plus the header, index and footer
+    assert!(std::fs::metadata(path).unwrap().len() < turns / 4 + 1000);
+
+    let mut reader = HistoryReader::open(path).unwrap();
+    assert_eq!(reader.header(), &header);
+    assert_eq!(reader.turns(), turns);
+    let range = BLOCK_TURNS - 3..BLOCK_TURNS + 6;
+    assert_eq!(reader.read_turns(range.clone()).unwrap(), all[range.start as usize..range.end as usize]);
+    assert!(reader.read_turns(turns - 1..turns + 1).is_err());
+
+    let payoffs = PayoffMatrix::default();
+    let score1_before = |turn: u64| all[..turn as usize].iter().map(|&(m1, m2)| payoffs.payoffs(m1, m2).0 as i64).sum::<i64>();
+    for turn in [0, 1, BLOCK_TURNS, BLOCK_TURNS + 17, turns] {
+        assert_eq!(reader.totals_before(turn).unwrap().score1, score1_before(turn));
+    }
+    assert_eq!(reader.totals().cooperations2, all.iter().filter(|&&(_, m2)| m2 == Move::Cooperate).count() as u64);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn truncated_binary_history_is_rejected() {
+    let path = temp_path("truncated.ipdh");
+    let path = path.to_str().unwrap();
+    let mut writer = HistoryWriter::create(path, &HistoryHeader::new("A", "B", PayoffMatrix::default())).unwrap();
+    for (move1, move2) in moves(100) {
+        writer.push(move1, move2).unwrap();
+    }
+    writer.finish().unwrap();
+    let bytes = std::fs::read(path).unwrap();
+    std::fs::write(path, &bytes[..bytes.len() - 10]).unwrap();
+
+    let error = HistoryReader::open(path).err().unwrap();
+    assert!(error.contains("not a valid binary history"), "{}", error);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn conversions_keep_moves_names_and_settings() {
+    let binary = temp_path("conversion.ipdh");
+    let (csv, json, back) = (temp_path("conversion.csv"), temp_path("conversion.json"), temp_path("back.ipdh"));
+    let [binary, csv, json, back] = [&binary, &csv, &json, &back].map(|path| path.to_str().unwrap().to_string());
+
+    let mut header = HistoryHeader::new("Win-Stay, Lose-Shift", "Tit for Tat", PayoffMatrix { reward: 4, temptation: 7, sucker: -1, punishment: 0 });
+    header.noise = Some(0.05);
+    let mut writer = HistoryWriter::create(&binary, &header).unwrap();
+    for (move1, move2) in moves(50) {
+        writer.push(move1, move2).unwrap();
+    }
+    writer.finish().unwrap();
+
+    assert_eq!(convert_history(&binary, &csv, Some(10..30)).unwrap(), 20);
+    assert_eq!(convert_history(&csv, &json, None).unwrap(), 20);
+    assert_eq!(convert_history(&json, &back, None).unwrap(), 20);
+
+    let history = read_history(&back, None).unwrap();
+    assert_eq!(history.header.strategy1, "Win-Stay, Lose-Shift");
+    assert_eq!(history.header.payoffs, header.payoffs);
+    assert_eq!(history.header.noise, Some(0.05));
+    assert_eq!(history.header.first_turn, 11);
+    let expected: Vec<(Move, Move)> = moves(50)[10..30].to_vec();
+    assert_eq!(history.history1.into_iter().zip(history.history2).collect::<Vec<_>>(), expected);
+
+    for path in [binary, csv, json, back] {
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,64 @@
+use iterated_prisoners_dilemma_lib::interactive::{SessionConfig, play_session};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::{Move, Strategy};
+use std::io::Cursor;
+
+// Plays a session against Tit for Tat with the given input, returning the result and everything printed
+fn session(input: &str, turns: Option<usize>, hidden_opponent: bool) -> (Vec<Move>, Vec<Move>, i64, i64, String) {
+    let create_opponent = || -> Result<Box<dyn Strategy>, String> { create_strategy_by_name("Tit for Tat", None) };
+    let config = SessionConfig { turns, hidden_opponent };
+    let mut output = Vec::new();
+    let (result, name) = play_session(&create_opponent, &config, &mut Cursor::new(input), &mut output).unwrap();
+    assert_eq!(name, "Tit for Tat");
+    (result.history1, result.history2, result.total1, result.total2, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn undo_restores_the_scores_and_history() {
+    let (human, opponent, total1, total2, output) = session("d\nc\nu\nd\nq\n", None, false);
+    assert_eq!(human, [Move::Defect, Move::Defect]);
+    // The rebuilt Tit for Tat remembers the first defection
+    assert_eq!(opponent, [Move::Cooperate, Move::Defect]);
+    assert_eq!((total1, total2), (5 + 1, 1));
+    let after_undo = &output[output.find("Last turn undone").unwrap()..];
+    assert!(after_undo.contains("You:      D\n") && after_undo.contains("Score: you 5 - 0"), "{}", after_undo);
+}
+
+#[test]
+fn nothing_to_undo_on_the_first_turn() {
+    let (human, _, _, _, output) = session("u\nc\n", Some(1), false);
+    assert!(output.contains("Nothing to undo"));
+    assert_eq!(human, [Move::Cooperate]);
+}
+
+#[test]
+fn hidden_opponents_are_only_named_at_the_end() {
+    let (_, _, _, _, output) = session("c\nd\n", Some(2), true);
+    let (during, after) = output.split_at(output.find("You were playing against: Tit for Tat").unwrap());
+    assert!(!during.contains("Tit for Tat"), "{}", during);
+    assert!(during.contains("You are playing against: Opponent"));
+    assert!(after.ends_with("Tit for Tat\n"));
+
+    let (_, _, _, _, output) = session("c\n", Some(1), false);
+    assert!(output.contains("You are playing against: Tit for Tat") && !output.contains("You were playing against"));
+}
+
+#[test]
+fn quitting_or_running_out_of_input_ends_the_session() {
+    let (human, _, total1, total2, _) = session("c\nd\nq\nc\n", None, false);
+    assert_eq!(human, [Move::Cooperate, Move::Defect]);
+    assert_eq!((total1, total2), (3 + 5, 3));
+
+    let (human, _, _, _, _) = session("c\ncooperate\n", None, false);
+    assert_eq!(human.len(), 2);
+    let (human, _, _, _, _) = session("", Some(10), false);
+    assert!(human.is_empty());
+}
+
+#[test]
+fn invalid_input_is_prompted_again() {
+    let (human, _, _, _, output) = session("x\n\nC\n", Some(1), false);
+    assert!(output.contains("Unknown command 'x'") && output.contains("Unknown command ''"));
+    assert_eq!(output.matches("Turn 1/1 [c/d/u/q]: ").count(), 3);
+    assert_eq!(human, [Move::Cooperate]);
+}
@@ -0,0 +1,49 @@
+use iterated_prisoners_dilemma_lib::Move;
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::strategies::lookup_key::KeyLayout;
+use serde_json::json;
+
+fn moves(description: &str) -> Vec<Move> {
+    description.chars().map(|c| if c == 'C' { Move::Cooperate } else { Move::Defect }).collect()
+}
+
+#[test]
+fn keys_round_trip_through_the_bit_encoding() {
+    let layout = KeyLayout { own_length: 1, opp_length: 2, initial_length: 1 };
+    assert_eq!(layout.table_size(), 16);
+    for key in 0..layout.table_size() {
+        // e.g. "own: C, opp: CD, opp_initial: D"
+        let description = layout.describe_key(key);
+        let parts: Vec<&str> = description.split(", ").map(|part| part.split(": ").nth(1).unwrap()).collect();
+        let (own, opp, initial) = (parts[0], parts[1], parts[2]);
+
+        let as_array = |moves: &str| moves.chars().map(|c| c.to_string()).collect::<Vec<_>>();
+        let entry = json!({ "own": as_array(own), "opp": as_array(opp), "opp_initial": as_array(initial) });
+        assert_eq!(layout.entry_key(&entry), Ok(key), "{}", description);
+
+        // Three turns: the opponent's first move, then its last two
+        let own_history = moves(&format!("CC{}", own));
+        let opponent_history = moves(&format!("{}{}", initial, opp));
+        assert_eq!(layout.key(&own_history, &opponent_history), Some(key), "{}", description);
+    }
+}
+
+#[test]
+fn missing_entries_use_the_default_move() {
+    // Tit for Tat without the entry after mutual defection, which defaults to cooperating
+    let table = json!([
+        { "own": ["C"], "opp": ["C"], "move": "C" },
+        { "own": ["C"], "opp": ["D"], "move": "D" },
+        { "own": ["D"], "opp": ["C"], "move": "C" },
+    ]);
+    let error = create_strategy_by_name("Looker Up", Some(json!({ "history_length": 1, "lookup_table": table.clone() }))).err().unwrap();
+    assert!(error.contains("Incomplete lookup table"), "{}", error);
+
+    let params = json!({ "history_length": 1, "lookup_table": table, "default": "C" });
+    let mut looker_up = create_strategy_by_name("Looker Up", Some(params)).unwrap();
+    let mut defector = create_strategy_by_name("Always Defect", None).unwrap();
+    let result = play_match(looker_up.as_mut(), defector.as_mut(), &MatchConfig::new(5), |_| {}).unwrap();
+    // CD -> D (from the table), DD -> C (the default)
+    assert_eq!(result.history1, moves("CDCDC"));
+}
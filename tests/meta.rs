@@ -0,0 +1,53 @@
+use iterated_prisoners_dilemma_lib::{Move, Strategy};
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::{Value, json};
+
+fn meta(name: &str, team: Value) -> Box<dyn Strategy> {
+    create_strategy_by_name(name, Some(json!({ "team": team }))).unwrap()
+}
+
+// Moves of `player` against `opponent`, as a string of C/D
+fn moves(player: &mut dyn Strategy, opponent: &str, turns: usize) -> String {
+    let mut opponent = create_strategy_by_name(opponent, None).unwrap();
+    let result = play_match(player, opponent.as_mut(), &MatchConfig::new(turns), |_| {}).unwrap();
+    result.history1.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect()
+}
+
+#[test]
+fn majority_ties_cooperate() {
+    let mut majority = meta("Meta Majority", json!(["Always Cooperate", "Always Defect"]));
+    assert_eq!(moves(majority.as_mut(), "Always Defect", 5), "CCCCC");
+    let mut majority = meta("Meta Majority", json!(["Always Cooperate", "Always Defect", "Always Defect"]));
+    assert_eq!(moves(majority.as_mut(), "Always Cooperate", 5), "DDDDD");
+}
+
+#[test]
+fn winner_follows_the_best_scorer_and_ties_go_to_the_earlier_member() {
+    // Nobody has scored on the first turn: the first member decides
+    let mut winner = meta("Meta Winner", json!(["Always Cooperate", "Always Defect"]));
+    assert_eq!(moves(winner.as_mut(), "Always Cooperate", 4), "CDDD");
+    let mut winner = meta("Meta Winner", json!(["Always Defect", "Always Cooperate"]));
+    assert_eq!(moves(winner.as_mut(), "Always Cooperate", 4), "DDDD");
+    // Tit for Tat would score 3 per turn against a cooperator, Always Defect 5
+    let mut winner = meta("Meta Winner", json!(["Tit for Tat", "Always Defect"]));
+    assert_eq!(moves(winner.as_mut(), "Always Cooperate", 4), "CDDD");
+}
+
+#[test]
+fn scores_start_over_every_match() {
+    let mut winner = meta("Meta Winner", json!(["Always Cooperate", "Always Defect"]));
+    assert_eq!(moves(winner.as_mut(), "Always Cooperate", 10), "CDDDDDDDDD");
+    // Always Defect's lead from the previous match is forgotten
+    assert_eq!(moves(winner.as_mut(), "Always Defect", 3), "CDD");
+}
+
+#[test]
+fn invalid_teams_are_rejected() {
+    let error = |params: Value| create_strategy_by_name("Meta Majority", Some(params)).err().unwrap();
+    assert!(error(json!({})).contains("Missing or invalid 'team'"));
+    assert!(error(json!({ "team": [] })).contains("at least one strategy"));
+    assert!(error(json!({ "team": ["Tit for Tat", 5] })).contains("must be a strategy name or"));
+    assert!(error(json!({ "team": [{ "params": {} }] })).contains("must have a 'strategy' name"));
+    assert!(error(json!({ "team": ["Tit for Tat", "No Such Strategy"] })).contains("Unknown strategy: 'No Such Strategy'"));
+}
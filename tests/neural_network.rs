@@ -0,0 +1,40 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::{Move, seed_rng};
+use serde_json::json;
+
+#[test]
+fn cooperation_rates_start_over_every_match() {
+    // Cooperates while the opponent cooperated more than half of the time (the inputs are the own and the
+    // opponent cooperation rates, and the turn)
+    let params = json!({
+        "history_length": 0, "threshold": 0.5, "layers": [3, 1], "weights": [[[0.0, 1.0, 0.0]]], "biases": [[0.0]]
+    });
+    let mut network = create_strategy_by_name("Neural Network", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Random", None).unwrap();
+    seed_rng(Some(5));
+    for _ in 0..3 {
+        let mut moves = Vec::new();
+        play_match(network.as_mut(), opponent.as_mut(), &MatchConfig::new(50), |turn| moves.push((turn.move1, turn.move2))).unwrap();
+        for (turn, &(own, _)) in moves.iter().enumerate() {
+            let cooperations = moves[..turn].iter().filter(|(_, opp)| *opp == Move::Cooperate).count();
+            let expected = if turn > 0 && cooperations as f64 / turn as f64 > 0.5 { Move::Cooperate } else { Move::Defect };
+            assert_eq!(own, expected, "turn {}", turn + 1);
+        }
+    }
+    seed_rng(None);
+}
+
+#[test]
+fn shorter_histories_are_counted_again() {
+    // Cooperates while it cooperated less than half of the time
+    let params = json!({
+        "history_length": 0, "threshold": -0.5, "layers": [3, 1], "weights": [[[-1.0, 0.0, 0.0]]], "biases": [[0.0]]
+    });
+    let mut network = create_strategy_by_name("Neural Network", Some(params)).unwrap();
+    let (c, d) = (Move::Cooperate, Move::Defect);
+    assert_eq!(network.next_move(&[c, c, c, c], &[c, c, c, c]), Move::Defect);
+    // A shorter history (e.g. a rebuilt match) that did not start from an empty one
+    assert_eq!(network.next_move(&[d, d], &[c, c]), Move::Cooperate);
+    assert_eq!(network.next_move(&[d, d, c], &[c, c, c]), Move::Cooperate);
+}
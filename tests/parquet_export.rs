@@ -0,0 +1,61 @@
+#![cfg(feature = "parquet")]
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int32Type, UInt64Type};
+use arrow_array::RecordBatch;
+use iterated_prisoners_dilemma_lib::parquet_export::{MatchInfo, ParquetExport, BATCH_ROWS};
+use iterated_prisoners_dilemma_lib::simulation::{play_match, MatchConfig};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+
+fn read_batches(path: &str) -> Vec<RecordBatch> {
+    ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap()
+        .build().unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn turns_and_matches_are_exported_with_typed_columns() {
+    let folder = std::env::temp_dir().join(format!("ipd-parquet-test-{}", std::process::id()));
+    let folder = folder.to_str().unwrap();
+    let mut export = ParquetExport::create(folder, "test").unwrap();
+
+    // More turns than a batch, so that several batches are written
+    let config = MatchConfig::new(BATCH_ROWS + 10).with_noise(0.1);
+    let mut strategy1 = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let mut strategy2 = create_strategy_by_name("Always Defect", None).unwrap();
+    let info = MatchInfo { repetition: 1, seed: Some(7), noise: config.noise, player1: "Tit for Tat", player2: "Always Defect" };
+    let result = play_match(strategy1.as_mut(), strategy2.as_mut(), &config, |turn| export.add_turn(&info, turn).unwrap()).unwrap();
+    export.add_match(&info, &result).unwrap();
+    let paths = export.finish().unwrap();
+    assert!(paths[0].ends_with("test_turns.parquet") && paths[1].ends_with("test_matches.parquet"));
+
+    let turns = read_batches(&paths[0]);
+    assert_eq!(turns.iter().map(RecordBatch::num_rows).sum::<usize>(), config.turns);
+    let schema = turns[0].schema();
+    let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+    assert_eq!(names, ["match", "repetition", "seed", "noise", "player1", "player2", "turn", "cooperate1", "cooperate2", "payoff1", "payoff2", "flipped1", "flipped2"]);
+    let score1: i64 = turns.iter()
+        .flat_map(|batch| batch.column_by_name("payoff1").unwrap().as_primitive::<Int32Type>().values().to_vec())
+        .map(i64::from)
+        .sum();
+    assert_eq!(score1, result.total1);
+    let flips2 = turns.iter()
+        .map(|batch| batch.column_by_name("flipped2").unwrap().as_boolean().true_count() as u64)
+        .sum::<u64>();
+    // Always Defect only cooperates by mistake
+    let cooperations2 = turns.iter()
+        .map(|batch| batch.column_by_name("cooperate2").unwrap().as_boolean().true_count() as u64)
+        .sum::<u64>();
+    assert_eq!(flips2, cooperations2);
+
+    let matches = read_batches(&paths[1]);
+    assert_eq!(matches[0].num_rows(), 1);
+    let column = |name: &str| matches[0].column_by_name(name).unwrap().as_primitive::<UInt64Type>().value(0);
+    assert_eq!(column("turns"), config.turns as u64);
+    assert_eq!(column("flips2"), flips2);
+    assert_eq!(column("seed"), 7);
+
+    let _ = std::fs::remove_dir_all(folder);
+}
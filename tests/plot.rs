@@ -0,0 +1,37 @@
+use iterated_prisoners_dilemma_lib::plot::{cumulative_score_chart, cumulative_scores, payoff_heatmap, rolling_cooperation};
+use iterated_prisoners_dilemma_lib::simulation::MatchResult;
+use iterated_prisoners_dilemma_lib::{Move, PayoffMatrix};
+
+const C: Move = Move::Cooperate;
+const D: Move = Move::Defect;
+
+#[test]
+fn cumulative_scores_add_up_the_payoffs() {
+    let result = MatchResult { history1: vec![C, D, D], history2: vec![C, C, D], total1: 9, total2: 4 };
+    let (scores1, scores2) = cumulative_scores(&result, &PayoffMatrix::default());
+    assert_eq!(scores1, vec![3.0, 8.0, 9.0]);
+    assert_eq!(scores2, vec![3.0, 3.0, 4.0]);
+}
+
+#[test]
+fn score_chart_shows_negative_scores() {
+    let payoffs = PayoffMatrix { reward: 3, temptation: 5, sucker: -2, punishment: 1 };
+    let result = MatchResult { history1: vec![C, C, D], history2: vec![D, D, D], total1: -3, total2: 11 };
+    let svg = cumulative_score_chart("Victim", "Defector", &result, &payoffs);
+    // The y axis goes from the lowest score (-4, after 2 turns) to the highest one
+    assert!(svg.contains(">-4<") && svg.contains(">11<"));
+}
+
+#[test]
+fn rolling_cooperation_only_counts_the_window() {
+    assert_eq!(rolling_cooperation(&[C, D, D, C, C], 2), vec![1.0, 0.5, 0.0, 0.5, 1.0]);
+}
+
+#[test]
+fn heatmap_escapes_labels_and_leaves_missing_pairs_empty() {
+    let labels = vec!["A & B".to_string(), "<C>".to_string()];
+    let svg = payoff_heatmap(&labels, &[vec![None, Some(3.0)], vec![Some(1.25), None]], 0.0, 5.0);
+    assert!(svg.contains("A &amp; B") && svg.contains("&lt;C&gt;"));
+    assert!(svg.contains(">3.00<") && svg.contains(">1.25<"));
+    assert_eq!(svg.matches("#eeeeee").count(), 2);
+}
@@ -0,0 +1,19 @@
+use iterated_prisoners_dilemma_lib::PayoffMatrix;
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::json;
+
+#[test]
+fn rewards_are_the_payoffs_of_the_match() {
+    // Learns the exact reward of every turn, and never explores
+    let params = json!({ "learning_rate": 1.0, "discount": 0.0, "exploration_rate": 0.0 });
+    let mut learner = create_strategy_by_name("Q-Learning", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Always Cooperate", None).unwrap();
+    let mut config = MatchConfig::new(2);
+    config.payoffs = PayoffMatrix { reward: 7, temptation: 9, sucker: -1, punishment: 2 };
+    play_match(learner.as_mut(), opponent.as_mut(), &config, |_| {}).unwrap();
+
+    // Cooperating on the first turn earned the reward of these payoffs
+    let q_table = &learner.export_parameters().unwrap()["q_table"];
+    assert_eq!(q_table[""], json!([7.0, 0.0]));
+}
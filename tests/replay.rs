@@ -0,0 +1,66 @@
+use iterated_prisoners_dilemma_lib::Move;
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::json;
+use std::path::PathBuf;
+
+// A recording of 3 moves in a temporary file, removed when dropped
+struct Recording(PathBuf);
+
+impl Recording {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("ipd-replay-{}-{}.txt", name, std::process::id()));
+        std::fs::write(&path, "CDC\n").unwrap();
+        Self(path)
+    }
+
+    fn params(&self) -> serde_json::Value {
+        json!({ "file": self.0.display().to_string() })
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn running_out_of_moves_stops_the_match_with_an_error() {
+    let recording = Recording::new("error");
+    let mut replay = create_strategy_by_name("Replay", Some(recording.params())).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    assert!(play_match(replay.as_mut(), opponent.as_mut(), &MatchConfig::new(3), |_| {}).is_ok());
+
+    let error = play_match(replay.as_mut(), opponent.as_mut(), &MatchConfig::new(5), |_| {}).err().unwrap();
+    assert!(error.contains("has only 3 moves, but turn 4 was requested"), "{}", error);
+}
+
+#[test]
+fn other_ends_can_be_chosen() {
+    let recording = Recording::new("loop");
+    let mut params = recording.params();
+    params["on_end"] = json!("loop");
+    let mut replay = create_strategy_by_name("Replay", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let result = play_match(replay.as_mut(), opponent.as_mut(), &MatchConfig::new(7), |_| {}).unwrap();
+    let moves: String = result.history1.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect();
+    assert_eq!(moves, "CDCCDCC");
+}
+
+#[test]
+fn an_empty_replay_fails_on_its_first_move() {
+    let mut replay = create_strategy_by_name("Replay", None).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let error = play_match(replay.as_mut(), opponent.as_mut(), &MatchConfig::new(1), |_| {}).err().unwrap();
+    assert!(error.contains("has only 0 moves, but turn 1 was requested"), "{}", error);
+}
+
+#[test]
+fn wrapped_strategies_pass_errors_on() {
+    let recording = Recording::new("wrapped");
+    let mut replay = create_strategy_by_name("flip(replay)", Some(recording.params())).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let error = play_match(replay.as_mut(), opponent.as_mut(), &MatchConfig::new(5), |_| {}).err().unwrap();
+    assert!(error.contains("turn 4 was requested"), "{}", error);
+}
@@ -0,0 +1,28 @@
+use iterated_prisoners_dilemma_lib::report::{html_report, ReportInfo};
+use iterated_prisoners_dilemma_lib::simulation::{play_match, MatchConfig};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+
+#[test]
+fn report_is_self_contained_and_escapes_names() {
+    let config = MatchConfig::new(200).with_noise(0.1);
+    let mut strategy1 = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let mut strategy2 = create_strategy_by_name("Random", None).unwrap();
+    let result = play_match(strategy1.as_mut(), strategy2.as_mut(), &config, |_| {}).unwrap();
+
+    let info = ReportInfo {
+        strategy1: "<Tit for Tat>",
+        strategy2: "Random & co",
+        config: &config,
+        exact: None,
+        datetime: "2024-01-01 00:00:00",
+    };
+    let html = html_report(&info, &result);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("&lt;Tit for Tat&gt;"));
+    assert!(html.contains("Random &amp; co"));
+    assert!(!html.contains("<script"));
+    // The only URL is the SVG namespace, nothing is fetched when opening the report
+    assert_eq!(html.matches("http").count(), html.matches("http://www.w3.org/2000/svg").count());
+    assert!(html.contains(&result.total1.to_string()));
+}
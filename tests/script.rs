@@ -0,0 +1,16 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use serde_json::json;
+
+#[test]
+fn endless_loops_stop_the_match_with_an_error() {
+    let path = std::env::temp_dir().join(format!("ipd-script-loop-{}.rhai", std::process::id()));
+    std::fs::write(&path, "fn next_move(own, opp) { loop { } }\n").unwrap();
+    let params = json!({ "file": path.display().to_string(), "max_operations": 1000 });
+    let mut script = create_strategy_by_name("Script", Some(params)).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+
+    let error = play_match(script.as_mut(), opponent.as_mut(), &MatchConfig::new(5), |_| {}).err().unwrap();
+    assert!(error.contains("(turn 1)") && error.contains("more than 1000 operations"), "{}", error);
+    std::fs::remove_file(&path).unwrap();
+}
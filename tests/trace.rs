@@ -0,0 +1,37 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::trace::{Reason, set_tracing, take_reasons};
+use serde_json::json;
+
+// Plays Gradual Tit For Tat against Always Defect, returning the reasons given on every turn
+fn reasons_per_turn(turns: usize) -> Vec<Vec<Reason>> {
+    let mut gradual = create_strategy_by_name("Gradual Tit For Tat", None).unwrap();
+    let mut defector = create_strategy_by_name("Always Defect", None).unwrap();
+    let mut reasons = Vec::new();
+    play_match(gradual.as_mut(), defector.as_mut(), &MatchConfig::new(turns), |_| reasons.push(take_reasons())).unwrap();
+    reasons
+}
+
+#[test]
+fn reasons_are_tagged_with_their_turn_and_player() {
+    set_tracing(true);
+    let reasons = reasons_per_turn(5);
+    set_tracing(false);
+
+    // Nothing to explain on the first turn, then a retaliation for the first defection
+    assert!(reasons[0].is_empty());
+    let retaliation = &reasons[1][0];
+    assert_eq!((retaliation.turn, retaliation.player), (2, 1));
+    assert_eq!(retaliation.message, "opponent defected, retaliating");
+    assert_eq!(retaliation.fields, vec![("new_defections", json!(1)), ("retaliation", json!(1))]);
+    assert_eq!(retaliation.describe(), "opponent defected, retaliating (new_defections=1, retaliation=1)");
+    // Then the apology
+    assert_eq!(reasons[2][0].message, "apologizing");
+    assert!(reasons.iter().flatten().all(|reason| reason.player == 1));
+}
+
+#[test]
+fn nothing_is_recorded_when_tracing_is_off() {
+    set_tracing(false);
+    assert!(reasons_per_turn(20).iter().all(Vec::is_empty));
+}
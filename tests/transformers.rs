@@ -0,0 +1,103 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::strategies::transformers::create_transformed_strategy;
+use iterated_prisoners_dilemma_lib::{Move, seed_rng};
+use serde_json::json;
+
+// Moves of the first player, as a string of C/D. The opponent "DDC" is a Cycler repeating D, D, C.
+fn moves_of(player: &str, opponent: &str, config: &MatchConfig) -> String {
+    let mut player = create_strategy_by_name(player, None).unwrap();
+    let mut opponent = match opponent {
+        "DDC" => create_strategy_by_name("Cycler", Some(json!({ "sequence": "DDC" }))).unwrap(),
+        name => create_strategy_by_name(name, None).unwrap(),
+    };
+    let result = play_match(player.as_mut(), opponent.as_mut(), config, |_| {}).unwrap();
+    result.history1.iter().map(|&m| if m == Move::Cooperate { 'C' } else { 'D' }).collect()
+}
+
+fn moves(player: &str, opponent: &str) -> String {
+    moves_of(player, opponent, &MatchConfig::new(6))
+}
+
+#[test]
+fn flip_plays_the_opposite_move() {
+    assert_eq!(moves("Tit for Tat", "DDC"), "CDDCDD");
+    assert_eq!(moves("flip(tit for tat)", "DDC"), "DCCDCC");
+}
+
+#[test]
+fn dual_mirrors_the_strategy() {
+    assert_eq!(moves("dual(always cooperate)", "DDC"), "DDDDDD");
+    // Tit for Tat only looks at the opponent: its dual is its flip
+    assert_eq!(moves("dual(tit for tat)", "DDC"), moves("flip(tit for tat)", "DDC"));
+    // Win-Stay, Lose-Shift is its own dual, but for its first move
+    let pavlov = "Win-Stay, Lose-Shift (Axelrod's original version)";
+    assert_eq!(moves(&format!("dual({})", pavlov), "DDC"), moves(&format!("initial(D, {})", pavlov), "DDC"));
+    assert_ne!(moves(&format!("dual({})", pavlov), "DDC"), moves(&format!("flip({})", pavlov), "DDC"));
+    assert_eq!(moves("dual(dual(grudge(1, always cooperate)))", "DDC"), moves("grudge(1, always cooperate)", "DDC"));
+}
+
+#[test]
+fn initial_plays_its_sequence_first() {
+    assert_eq!(moves("initial(DDC, always cooperate)", "Always Cooperate"), "DDCCCC");
+    assert_eq!(moves("initial(cD, always defect)", "Always Cooperate"), "CDDDDD");
+}
+
+#[test]
+fn final_defects_on_the_last_turns_when_the_length_is_known() {
+    assert_eq!(moves("final(2, always cooperate)", "Always Cooperate"), "CCCCDD");
+    let unknown_length = MatchConfig::new(6).with_length_known(false);
+    assert_eq!(moves_of("final(2, always cooperate)", "Always Cooperate", &unknown_length), "CCCCCC");
+}
+
+#[test]
+fn rua_retaliates_until_the_opponent_cooperates() {
+    assert_eq!(moves("rua(always cooperate)", "DDC"), "CDDCDD");
+}
+
+#[test]
+fn noise_flips_moves_with_its_probability() {
+    assert_eq!(moves("noise(0, always cooperate)", "Always Cooperate"), "CCCCCC");
+    assert_eq!(moves("noise(1, always cooperate)", "Always Cooperate"), "DDDDDD");
+
+    let config = MatchConfig::new(2000);
+    seed_rng(Some(34));
+    let noisy = moves_of("noise(0.2, always cooperate)", "Always Cooperate", &config);
+    seed_rng(Some(34));
+    assert_eq!(moves_of("noise(0.2, always cooperate)", "Always Cooperate", &config), noisy);
+    seed_rng(None);
+    let flipped = noisy.chars().filter(|&c| c == 'D').count();
+    assert!((350..450).contains(&flipped), "{} flipped moves out of 2000", flipped);
+}
+
+#[test]
+fn invalid_expressions_are_rejected() {
+    let error = |expression: &str| create_transformed_strategy(expression, None).err().unwrap();
+    assert!(error("noise(tit for tat)").contains("Invalid 'noise' transformer, expected something like 'noise(0.05, tit for tat)'"));
+    assert!(error("flip()").contains("Invalid 'flip' transformer"));
+    assert!(error("shuffle(tit for tat)").contains("is not a transformer expression"));
+    assert!(error("noise(1.5, tit for tat)").contains("Invalid probability '1.5'"));
+    assert!(error("grudge(-1, tit for tat)").contains("Invalid number '-1'"));
+    assert!(error("initial(CX, tit for tat)").contains("Invalid character in sequence: X"));
+    assert!(error("flip(tit for tat").contains("Missing closing parenthesis"));
+    assert!(error("noise(0.1, flip(tit for tat)").contains("Missing closing parenthesis in 'flip(tit for tat'"));
+    assert!(error("flip(no such strategy)").contains("Unknown strategy"));
+}
+
+#[test]
+fn grudges_start_over_every_match() {
+    let path = std::env::temp_dir().join(format!("ipd-transformers-grudge-{}.txt", std::process::id()));
+    std::fs::write(&path, "DCDCCC\n").unwrap();
+    let mut opponent = create_strategy_by_name("Replay", Some(json!({ "file": path.display().to_string() }))).unwrap();
+    let mut grudge = create_strategy_by_name("grudge(1, always cooperate)", None).unwrap();
+
+    for _ in 0..2 {
+        let mut moves = String::new();
+        play_match(grudge.as_mut(), opponent.as_mut(), &MatchConfig::new(6), |turn| {
+            moves.push(if turn.move1 == Move::Cooperate { 'C' } else { 'D' });
+        }).unwrap();
+        // Defects forever after the second defection
+        assert_eq!(moves, "CCCDDD");
+    }
+    std::fs::remove_file(&path).unwrap();
+}
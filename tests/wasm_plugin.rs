@@ -0,0 +1,117 @@
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::strategies::wasm_plugin::{WasmPlugin, register_plugins_in_dir, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES};
+use iterated_prisoners_dilemma_lib::Move;
+use std::path::{Path, PathBuf};
+
+const SAMPLE_PLUGIN: &str = "plugins/wasm_grim_trigger.wat";
+
+fn sample_plugin() -> WasmPlugin {
+    WasmPlugin::load(Path::new(SAMPLE_PLUGIN), DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES).expect("the sample plugin loads")
+}
+
+// Writes `contents` to a temporary `.wat` file and returns its path
+fn write_plugin(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ipd-wasm-plugin-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.wat", name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn sample_plugin_plays_grim_trigger() {
+    let mut plugin = sample_plugin().create().unwrap();
+    let mut opponent = create_strategy_by_name("initial(CCD, always cooperate)", None).unwrap();
+    let result = play_match(&mut plugin, opponent.as_mut(), &MatchConfig::new(6), |_| {}).unwrap();
+
+    use Move::{Cooperate as C, Defect as D};
+    assert_eq!(result.history1, vec![C, C, C, D, D, D]);
+    assert_eq!(result.history2, vec![C, C, D, C, C, C]);
+}
+
+#[test]
+fn sample_plugin_forgets_between_matches() {
+    let mut plugin = sample_plugin().create().unwrap();
+    let mut opponent = create_strategy_by_name("always defect", None).unwrap();
+    play_match(&mut plugin, opponent.as_mut(), &MatchConfig::new(3), |_| {}).unwrap();
+
+    // `ipd_reset` is called before the first move, so a new match starts cooperating again
+    let mut cooperator = create_strategy_by_name("always cooperate", None).unwrap();
+    let result = play_match(&mut plugin, cooperator.as_mut(), &MatchConfig::new(3), |_| {}).unwrap();
+    assert!(result.history1.iter().all(|&m| m == Move::Cooperate));
+}
+
+#[test]
+fn plugins_in_dir_are_usable_by_name() {
+    let names = register_plugins_in_dir(Path::new("plugins"), DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES).unwrap();
+    assert!(names.contains(&"wasm_grim_trigger".to_string()));
+
+    let mut plugin = create_strategy_by_name("noise(0, wasm_grim_trigger)", Some(serde_json::json!({}))).unwrap();
+    assert_eq!(plugin.next_move(&[Move::Cooperate], &[Move::Defect]), Move::Defect);
+}
+
+#[test]
+fn endless_loops_run_out_of_fuel() {
+    let path = write_plugin("endless_loop", r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "ipd_alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "ipd_next_move") (param i32 i32 i32) (result i32)
+            (loop $forever (br $forever))
+            (i32.const 0)))
+    "#);
+    let mut plugin = WasmPlugin::load(&path, 10_000, DEFAULT_MAX_MEMORY_BYTES).unwrap().create().unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let error = play_match(&mut plugin, opponent.as_mut(), &MatchConfig::new(5), |_| {}).err().unwrap();
+    assert!(error.contains("(turn 1)") && error.contains("ran out of fuel"), "{}", error);
+}
+
+#[test]
+fn memory_is_limited() {
+    // 32 pages of 64 KiB are 2 MiB
+    let path = write_plugin("big_memory", r#"
+        (module
+          (memory (export "memory") 32)
+          (func (export "ipd_alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "ipd_next_move") (param i32 i32 i32) (result i32) (i32.const 0)))
+    "#);
+    assert!(WasmPlugin::load(&path, DEFAULT_FUEL, 1024 * 1024).is_err());
+    assert!(WasmPlugin::load(&path, DEFAULT_FUEL, 4 * 1024 * 1024).is_ok());
+}
+
+#[test]
+fn plugins_cannot_import_host_functions() {
+    let path = write_plugin("with_import", r#"
+        (module
+          (import "env" "exit" (func (param i32)))
+          (memory (export "memory") 1)
+          (func (export "ipd_alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "ipd_next_move") (param i32 i32 i32) (result i32) (i32.const 0)))
+    "#);
+    let error = WasmPlugin::load(&path, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES).err().unwrap();
+    assert!(error.contains("cannot import"), "{}", error);
+}
+
+#[test]
+fn long_matches_see_the_whole_history() {
+    // Plays the opponent's move from two turns ago, so older moves must survive the history buffer growing
+    let path = write_plugin("two_turns_ago", r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "ipd_alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "ipd_next_move") (param $own i32) (param $opp i32) (param $len i32) (result i32)
+            (if (result i32) (i32.lt_u (local.get $len) (i32.const 2))
+              (then (i32.const 0))
+              (else (i32.eq (i32.load8_u (i32.sub (i32.add (local.get $opp) (local.get $len)) (i32.const 2)))
+                            (i32.const 68))))))
+    "#);
+    let mut plugin = WasmPlugin::load(&path, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES).unwrap().create().unwrap();
+    let mut opponent = create_strategy_by_name("Cycler", Some(serde_json::json!({ "sequence": "CCDCDDD" }))).unwrap();
+
+    for turns in [1000, 10] {
+        let result = play_match(&mut plugin, opponent.as_mut(), &MatchConfig::new(turns), |_| {}).unwrap();
+        assert!(result.history1[..2].iter().all(|&m| m == Move::Cooperate));
+        assert_eq!(result.history1[2..], result.history2[..turns - 2]);
+    }
+}
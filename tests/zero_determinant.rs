@@ -0,0 +1,93 @@
+use iterated_prisoners_dilemma_lib::analysis::exact_payoffs_for;
+use iterated_prisoners_dilemma_lib::simulation::{MatchConfig, play_match};
+use iterated_prisoners_dilemma_lib::strategies::create_strategy_by_name;
+use iterated_prisoners_dilemma_lib::strategies::zero_determinant::ZeroDeterminantStrategy;
+use iterated_prisoners_dilemma_lib::{PayoffMatrix, seed_rng};
+
+// Payoffs with which the ZD presets are still feasible
+const OTHER_PAYOFFS: PayoffMatrix = PayoffMatrix { reward: 4, temptation: 6, sucker: 0, punishment: 2 };
+
+#[test]
+fn zd_presets_enforce_their_linear_relation() {
+    let presets = [
+        ("ZD-Extort-2", 1.0 / 9.0, 0.5, 1.0),
+        ("ZD-Extort-4", 4.0 / 17.0, 0.25, 1.0),
+        ("ZD-GTFT-2", 0.25, 0.5, 3.0),
+        ("ZD-SET-2", 0.25, 0.0, 2.0),
+    ];
+    // Memory-one opponents, so that the long run scores are known exactly
+    let opponents = ["Random", "Tit for Tat", "Always Cooperate", "Win-Stay, Lose-Shift", "Probability Cooperator"];
+
+    for (name, phi, slope, baseline) in presets {
+        for opponent_name in opponents {
+            let zd = ZeroDeterminantStrategy::from_slope(name, phi, slope, baseline).unwrap();
+            let opponent = create_strategy_by_name(opponent_name, None).unwrap();
+            let exact = exact_payoffs_for(&zd, opponent.as_ref(), 0.0, &PayoffMatrix::default()).unwrap();
+            let predicted = zd.predicted_opponent_score(exact.payoff1);
+            assert!(
+                (exact.payoff2 - predicted).abs() < 1e-6,
+                "{} vs {}: opponent score {:.6}, theory predicts {:.6} (ZD score {:.6})",
+                name, opponent_name, exact.payoff2, predicted, exact.payoff1
+            );
+        }
+    }
+}
+
+#[test]
+fn zd_probabilities_follow_the_match_payoffs() {
+    let mut zd = create_strategy_by_name("ZD-Extort-2", None).unwrap();
+    let mut opponent = create_strategy_by_name("Random", None).unwrap();
+    let mut config = MatchConfig::new(50_000);
+    config.payoffs = OTHER_PAYOFFS;
+    seed_rng(Some(26));
+    let result = play_match(zd.as_mut(), opponent.as_mut(), &config, |_| {}).unwrap();
+    seed_rng(None);
+
+    // Extortion over the punishment payoff of these payoffs (2): S_opponent - 2 = (S_zd - 2) / 2
+    let (zd_score, opponent_score) = result.average_payoffs();
+    let predicted = 2.0 + 0.5 * (zd_score - 2.0);
+    assert!(
+        (opponent_score - predicted).abs() < 0.05,
+        "measured opponent score {:.4}, theory predicts {:.4} (ZD score {:.4})", opponent_score, predicted, zd_score
+    );
+    let expected = [8.0 / 9.0, 4.0 / 9.0, 4.0 / 9.0, 0.0];
+    let probabilities = zd.memory_one().unwrap();
+    for (p, e) in [probabilities.cc, probabilities.cd, probabilities.dc, probabilities.dd].iter().zip(expected) {
+        assert!((p - e).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn zd_strategies_infeasible_with_the_match_payoffs_stop_it() {
+    // ZD-SET-2 sets the opponent's score to 2, below the punishment payoff of these payoffs
+    let mut zd = create_strategy_by_name("ZD-SET-2", None).unwrap();
+    let mut opponent = create_strategy_by_name("Tit for Tat", None).unwrap();
+    let mut config = MatchConfig::new(10);
+    config.payoffs = PayoffMatrix { punishment: 3, ..OTHER_PAYOFFS };
+    let error = play_match(zd.as_mut(), opponent.as_mut(), &config, |_| {}).err().unwrap();
+    assert!(error.contains("ZD-SET-2 cannot be played with these payoffs"), "{}", error);
+}
+
+#[test]
+fn zd_probabilities_match_press_dyson_presets() {
+    let extort2 = ZeroDeterminantStrategy::from_slope("ZD-Extort-2", 1.0 / 9.0, 0.5, 1.0).unwrap();
+    let expected = [8.0 / 9.0, 0.5, 1.0 / 3.0, 0.0];
+    for (p, e) in extort2.probabilities().iter().zip(expected) {
+        assert!((p - e).abs() < 1e-12);
+    }
+
+    let gtft2 = ZeroDeterminantStrategy::from_slope("ZD-GTFT-2", 0.25, 0.5, 3.0).unwrap();
+    let expected = [1.0, 0.125, 1.0, 0.25];
+    for (p, e) in gtft2.probabilities().iter().zip(expected) {
+        assert!((p - e).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn zd_rejects_infeasible_parameters() {
+    assert!(ZeroDeterminantStrategy::from_slope("ZD", 1.0, 0.5, 1.0).is_err());
+    assert!(ZeroDeterminantStrategy::from_slope("ZD", 0.1, 1.5, 1.0).is_err());
+    assert!(ZeroDeterminantStrategy::from_slope("ZD", 0.1, 0.5, 4.0).is_err());
+    assert!(ZeroDeterminantStrategy::from_extortion("ZD", 0.1, 0.5).is_err());
+    assert!(create_strategy_by_name("zd", Some(serde_json::json!({ "phi": 0.1, "chi": 3 }))).is_ok());
+}